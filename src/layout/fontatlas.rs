@@ -3,9 +3,13 @@ use fontdue::*;
 
 pub struct FontAtlas {
     font: Font,
+    texture: TextureId,
     changed: bool,
     dimensions: (f32, f32),
 
+    // Physical pixels to a logical one
+    scale: f32,
+
     // Glyph info
     glyphsi: Vec<(char, f32, usize)>,
     bitmaps: Vec<Vec<u8>>,
@@ -21,8 +25,10 @@ impl FontAtlas {
 
         return FontAtlas {
             font,
+            texture: TextureId::unique(),
             changed: false,
             dimensions: (0., 0.),
+            scale: 1.,
 
             // Glyph info
             glyphsi: vec![],
@@ -32,21 +38,35 @@ impl FontAtlas {
         };
     }
 
+    /// Glyphs are rasterized this many times bigger than the size they are
+    /// asked for, so they line up with the physical pixels of the screen.
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale;
+    }
+
+    pub fn scale(&self) -> f32 {
+        return self.scale;
+    }
+
+    /// How much room the character takes up, in logical pixels.
     pub fn size(&mut self, chr: char, px: f32) -> Vec2 {
         let tex = self.get(chr, px);
         let metrics = self.metrics[tex];
 
         return Vec2 {
-            x: metrics.advance_width,
+            x: metrics.advance_width / self.scale,
             y: px,
         };
     }
 
+    /// The metrics of the rasterized glyph, in physical pixels.
     pub fn metrics(&self, glyphsi: usize) -> Metrics {
         return self.metrics[glyphsi];
     }
 
     pub fn get(&mut self, chr: char, px: f32) -> usize {
+        let px = px * self.scale;
+
         // Check if we already have this character
         for (c, p, i) in &self.glyphsi {
             if *c == chr && *p == px {
@@ -78,7 +98,11 @@ impl FontAtlas {
         );
     }
 
+    /// Packs every glyph into a texture. It only needs building again once
+    /// `texture_changed` says new glyphs were added.
     pub fn build_texture(&mut self) -> TextureData {
+        self.changed = false;
+
         // Sort glyphs by size
         self.glyphsi.sort_by(|a, b| {
             self.metrics[a.2]
@@ -146,6 +170,11 @@ impl FontAtlas {
         return TextureData::new(rgba, dimensions);
     }
 
+    /// The id the texture from `build_texture` should be uploaded as.
+    pub fn texture(&self) -> TextureId {
+        return self.texture;
+    }
+
     pub fn texture_changed(&self) -> bool {
         return self.changed;
     }
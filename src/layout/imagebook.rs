@@ -0,0 +1,77 @@
+use std::path::Path;
+
+use crate::ruglet::*;
+
+struct Image {
+    texture: TextureId,
+    size: Vec2,
+
+    // Waiting to be sent along with the next frame
+    pending: Option<TextureData>,
+
+    // Waiting to be freed along with the next frame
+    removing: bool,
+}
+
+pub struct ImageBook {
+    images: Vec<Image>,
+}
+
+impl ImageBook {
+    pub fn new() -> Self {
+        return ImageBook { images: vec![] };
+    }
+
+    pub fn add(&mut self, data: TextureData) -> usize {
+        let size = Vec2::new(data.dimensions.0 as f32, data.dimensions.1 as f32);
+
+        // Textures can't be empty, and an empty image is never drawn anyway
+        let empty = size.x == 0. || size.y == 0.;
+
+        self.images.push(Image {
+            texture: TextureId::unique(),
+            size,
+            pending: if empty { None } else { Some(data) },
+            removing: false,
+        });
+        return self.images.len() - 1;
+    }
+
+    /// Decodes a PNG or JPEG file and adds it.
+    #[allow(dead_code)]
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<usize, ImageError> {
+        let data = TextureData::load(path)?;
+        return Ok(self.add(data));
+    }
+
+    /// How big the image is, in pixels.
+    pub fn size(&self, id: usize) -> Vec2 {
+        return self.images[id].size;
+    }
+
+    pub fn texture(&self, id: usize) -> TextureId {
+        return self.images[id].texture;
+    }
+
+    /// Frees the texture of the image with the next frame. It shouldn't be
+    /// drawn again after that.
+    #[allow(dead_code)]
+    pub fn remove(&mut self, id: usize) {
+        self.images[id].pending = None;
+        self.images[id].removing = true;
+    }
+
+    /// Sends the images that haven't been uploaded yet along with the frame,
+    /// and lets go of the removed ones.
+    pub fn upload(&mut self, frame: &mut Frame) {
+        for image in &mut self.images {
+            if let Some(data) = image.pending.take() {
+                frame.set_texture(image.texture, data);
+            }
+            if image.removing {
+                frame.remove_texture(image.texture);
+                image.removing = false;
+            }
+        }
+    }
+}
@@ -1,7 +1,9 @@
 pub mod fontatlas;
+pub mod imagebook;
 pub mod textbook;
 pub mod tree;
 
 pub use fontatlas::*;
+pub use imagebook::*;
 pub use textbook::*;
 pub use tree::*;
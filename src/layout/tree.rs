@@ -1,10 +1,19 @@
+use std::time::Duration;
+
 use crate::ruglet::*;
 
-use super::{FontAtlas, TextBook};
+use super::{FontAtlas, ImageBook, TextBook};
+
+// In logical pixels
+const PX: f32 = 30.;
+pub const LH: f32 = 40.;
+const WS: f32 = 15.;
+
+// Smooth scrolling gets most of the way there in this many seconds
+const SCROLL_TIME: f32 = 0.06;
 
-const PX: f32 = 60.;
-const LH: f32 = 80.;
-const WS: f32 = 30.;
+// Long pauses between frames shouldn't skip the animation
+const MAX_STEP: f32 = 1. / 30.;
 
 //////////
 // NODE //
@@ -14,17 +23,65 @@ const WS: f32 = 30.;
 pub enum NodeKind {
     None,
     Text(usize),
+    Image(usize, Fit),
     Pad(f32),
     Scroll(f32),
     Clickable(usize),
 }
 
+/// How an image is scaled to the area of its node.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fit {
+    /// As big as it can be while still showing all of it
+    Contain,
+    /// As small as it can be while still filling the area, cropping the rest
+    Cover,
+    /// Stretched to the area
+    Fill,
+    /// At its own size, cropping whatever doesn't fit
+    None,
+}
+
+/// What fills the area of a node, underneath its children.
+#[derive(Debug, Clone)]
+pub enum Background {
+    Color(Color),
+    Gradient(Gradient),
+}
+
+impl From<Color> for Background {
+    fn from(color: Color) -> Self {
+        return Background::Color(color);
+    }
+}
+
+impl From<Gradient> for Background {
+    fn from(gradient: Gradient) -> Self {
+        return Background::Gradient(gradient);
+    }
+}
+
 #[derive(Clone)]
 struct Node {
     kind: NodeKind,
     child: usize,
     next: usize,
     area: Area,
+    background: Option<Background>,
+    layer: Option<i32>,
+    transform: Option<Transform>,
+
+    // Drawn instead of the background while the mouse is over the node
+    hover_background: Option<Background>,
+    hovered: bool,
+
+    // Where a scroll node is smoothly scrolling to, and how far it can go
+    scroll_target: Option<f32>,
+    scroll_max: f32,
+
+    // The atlas scale text was last measured at, which its area is only good for
+    text_scale: f32,
 }
 
 //////////
@@ -33,12 +90,22 @@ struct Node {
 
 pub struct Tree {
     nodes: Vec<Node>,
+
+    // Where the mouse was last seen, if it's in the window
+    mouse: Option<Vec2>,
+
+    // The innermost node under it
+    hovered: Option<usize>,
 }
 
 // Build function
 impl Tree {
     pub fn new() -> Self {
-        return Tree { nodes: vec![] };
+        return Tree {
+            nodes: vec![],
+            mouse: None,
+            hovered: None,
+        };
     }
 
     pub fn add(&mut self, kind: NodeKind, children: Vec<usize>) -> usize {
@@ -61,17 +128,54 @@ impl Tree {
             child: child_id,
             next: usize::MAX,
             area: Area::zero(),
+            background: None,
+            layer: None,
+            transform: None,
+            hover_background: None,
+            hovered: false,
+            scroll_target: None,
+            scroll_max: f32::MAX,
+            text_scale: 0.,
         });
 
         return id;
     }
 
+    #[allow(dead_code)]
     pub fn get(&self, id: usize) -> NodeKind {
         return self.nodes[id].kind.clone();
     }
 
+    /// Setting a scroll offset jumps straight there, see `scroll_by`.
+    #[allow(dead_code)]
     pub fn update(&mut self, node: usize, kind: NodeKind) {
-        self.nodes[node].kind = kind
+        self.nodes[node].kind = kind;
+        self.nodes[node].scroll_target = None;
+    }
+
+    /// Draws the node and its children on a different layer, so they can
+    /// end up on top of nodes that are drawn later.
+    #[allow(dead_code)]
+    pub fn set_layer(&mut self, node: usize, layer: i32) {
+        self.nodes[node].layer = Some(layer);
+    }
+
+    /// Moves, scales or rotates the node and its children where they are
+    /// drawn, without laying them out again. Clicks follow them there.
+    #[allow(dead_code)]
+    pub fn set_transform(&mut self, node: usize, transform: Transform) {
+        self.nodes[node].transform = Some(transform);
+    }
+
+    /// Fills the area of the node before its children are drawn.
+    pub fn set_background(&mut self, node: usize, background: impl Into<Background>) {
+        self.nodes[node].background = Some(background.into());
+    }
+
+    /// Fills the area of the node instead of its background while the mouse
+    /// is over it.
+    pub fn set_hover_background(&mut self, node: usize, background: impl Into<Background>) {
+        self.nodes[node].hover_background = Some(background.into());
     }
 
     pub fn replace(&mut self, a: usize, b: usize) {
@@ -94,34 +198,155 @@ impl Tree {
 
 // Event functions
 impl Tree {
+    /// The event of the clickable under the mouse. Where clickables overlap,
+    /// the one on the highest layer gets it, or the first one on the same
+    /// layer. Nodes that aren't clickable don't get in the way of clicks.
     pub fn click(&self, node: usize, mouse: Vec2) -> Option<usize> {
+        return self
+            .click_layer(node, mouse, 0)
+            .map(|(event_id, _)| event_id);
+    }
+
+    // Along with the layer the clickable is drawn on
+    fn click_layer(&self, node: usize, mouse: Vec2, layer: i32) -> Option<(usize, i32)> {
+        let layer = self.nodes[node].layer.unwrap_or(layer);
+
+        // Undo the transform, to get back to where the node was laid out
+        let mouse = match self.nodes[node].transform {
+            Some(transform) => transform.inverse().apply(mouse),
+            None => mouse,
+        };
+
         if !mouse.inside(self.nodes[node].area) {
             return None;
         }
 
         match self.nodes[node].kind {
             NodeKind::Clickable(event_id) => {
-                return Some(event_id);
+                return Some((event_id, layer));
             }
             _ => {
                 let mut child = self.nodes[node].child;
+                let mut hit = None;
 
                 while child != usize::MAX {
-                    let res = self.click(child, mouse);
-
-                    if res.is_some() {
-                        return res;
-                    }
-
+                    hit = topmost(hit, self.click_layer(child, mouse, layer));
                     child = self.nodes[child].next;
                 }
 
-                return None;
+                return hit;
             }
         };
     }
 }
 
+// Scroll functions
+impl Tree {
+    /// Smoothly scrolls a scroll node by `delta`, on top of wherever it was
+    /// already scrolling to. It gets there through `animate`.
+    pub fn scroll_by(&mut self, node: usize, delta: f32) {
+        let NodeKind::Scroll(scroll) = self.nodes[node].kind else {
+            return;
+        };
+
+        let target = self.nodes[node].scroll_target.unwrap_or(scroll) + delta;
+        self.nodes[node].scroll_target = Some(target.clamp(0., self.nodes[node].scroll_max));
+    }
+
+    /// Moves the scroll nodes `dt` further towards where they are scrolling
+    /// to. Returns whether any of them still have a way to go.
+    pub fn animate(&mut self, dt: Duration) -> bool {
+        let dt = dt.as_secs_f32().min(MAX_STEP);
+
+        let mut moving = false;
+        for node in &mut self.nodes {
+            let (NodeKind::Scroll(scroll), Some(target)) = (&mut node.kind, node.scroll_target)
+            else {
+                continue;
+            };
+
+            // Slows down as it gets closer, whatever the frame rate
+            *scroll += (target - *scroll) * (1. - (-dt / SCROLL_TIME).exp());
+
+            if (target - *scroll).abs() < 0.5 {
+                *scroll = target;
+                node.scroll_target = None;
+            } else {
+                moving = true;
+            }
+        }
+
+        return moving;
+    }
+}
+
+// Hover functions
+impl Tree {
+    /// Moves the mouse to `mouse`, or out of the window with None. Returns
+    /// whether that changed which nodes are hovered, and so how they look.
+    pub fn hover(&mut self, root: usize, mouse: Option<Vec2>) -> bool {
+        self.mouse = mouse;
+
+        let before = self.hovered;
+        let changed = self.update_hover(root, mouse);
+        self.hovered = self.innermost_hovered(root, 0).map(|(node, _)| node);
+
+        return changed || before != self.hovered;
+    }
+
+    /// The innermost node under the mouse.
+    #[allow(dead_code)]
+    pub fn hovered(&self) -> Option<usize> {
+        return self.hovered;
+    }
+
+    /// Whether the mouse is over the node, or over one of its children.
+    #[allow(dead_code)]
+    pub fn is_hovered(&self, node: usize) -> bool {
+        return self.nodes[node].hovered;
+    }
+
+    // Every node is visited, so the ones the mouse left stop being hovered
+    fn update_hover(&mut self, node: usize, mouse: Option<Vec2>) -> bool {
+        // Same as a click, in the space the node was laid out in
+        let mouse = mouse
+            .map(|mouse| match self.nodes[node].transform {
+                Some(transform) => transform.inverse().apply(mouse),
+                None => mouse,
+            })
+            .filter(|mouse| mouse.inside(self.nodes[node].area));
+
+        let hovered = mouse.is_some();
+        let mut changed = self.nodes[node].hovered != hovered;
+        self.nodes[node].hovered = hovered;
+
+        let mut child = self.nodes[node].child;
+        while child != usize::MAX {
+            changed |= self.update_hover(child, mouse);
+            child = self.nodes[child].next;
+        }
+
+        return changed;
+    }
+
+    // Overlapping children are picked like with clicks, by layer
+    fn innermost_hovered(&self, node: usize, layer: i32) -> Option<(usize, i32)> {
+        if !self.nodes[node].hovered {
+            return None;
+        }
+        let layer = self.nodes[node].layer.unwrap_or(layer);
+
+        let mut child = self.nodes[node].child;
+        let mut hovered = None;
+        while child != usize::MAX {
+            hovered = topmost(hovered, self.innermost_hovered(child, layer));
+            child = self.nodes[child].next;
+        }
+
+        return hovered.or(Some((node, layer)));
+    }
+}
+
 // Debug functions
 impl Tree {
     pub fn print(&self, node: usize, tab: usize) {
@@ -142,75 +367,155 @@ impl Tree {
         frame: &mut Frame,
         atlas: &mut FontAtlas,
         text: &TextBook,
+        images: &mut ImageBook,
     ) {
+        // Text is measured and rasterized for the screen it ends up on
+        atlas.set_scale(frame.scale);
+
         // layout
-        self.layout(root, frame.area, atlas, text);
+        self.layout(root, frame.area, atlas, text, images);
         self.nodes[root].area = frame.area;
 
+        // Things may have moved under the mouse, like when scrolling
+        self.hover(root, self.mouse);
+
         // Update the texture if new glyphs have been added to the font atlas
         if atlas.texture_changed() {
-            frame.set_texture(atlas.build_texture());
+            frame.set_texture(atlas.texture(), atlas.build_texture());
         }
 
+        // Same for any images that were added
+        images.upload(frame);
+
         // render
-        self.render(frame, atlas, text);
+        self.render(root, frame, atlas, text, images);
     }
 
-    fn render(&self, frame: &mut Frame, atlas: &mut FontAtlas, text: &TextBook) {
-        for node in &self.nodes {
-            if !frame.area.contains(node.area) || frame.area.is_zero() {
-                continue;
-            }
+    fn render(
+        &self,
+        node: usize,
+        frame: &mut Frame,
+        atlas: &mut FontAtlas,
+        text: &TextBook,
+        images: &ImageBook,
+    ) {
+        // The layer applies to the whole subtree
+        let parent_layer = frame.layer();
+        if let Some(layer) = self.nodes[node].layer {
+            frame.set_layer(layer);
+        }
+
+        // So does the transform
+        if let Some(transform) = self.nodes[node].transform {
+            frame.push_transform(transform);
+        }
+
+        // Parents are drawn before their children, so they end up underneath
+        if frame.on_screen(self.nodes[node].area) && !frame.area.is_zero() {
+            self.render_node(&self.nodes[node], frame, atlas, text, images);
+        }
+
+        // Scrolled content shouldn't spill out of the scroll area
+        let clip = matches!(self.nodes[node].kind, NodeKind::Scroll(_));
+        if clip {
+            frame.push_clip(self.nodes[node].area);
+        }
+
+        let mut child = self.nodes[node].child;
+        while child != usize::MAX {
+            self.render(child, frame, atlas, text, images);
+            child = self.nodes[child].next;
+        }
+
+        if clip {
+            frame.pop_clip();
+        }
 
-            match node.kind {
-                NodeKind::Text(tid) => {
-                    let mut x = node.area.0.x;
-                    let mut y = node.area.0.y;
-
-                    for word in text.get(tid).split_whitespace() {
-                        let w: f32 = word.chars().map(|c| atlas.size(c, PX).x).sum();
-                        if x + w + WS > node.area.1.x {
-                            x = node.area.0.x;
-                            y += LH;
-                        }
-
-                        for c in word.chars() {
-                            let texture = atlas.get(c, PX);
-                            let metrics = atlas.metrics(texture);
-
-                            let gx = x + metrics.xmin as f32;
-                            let gy = y + LH - metrics.ymin as f32 - metrics.height as f32;
-                            frame.quad(
-                                Area(
-                                    Vec2::new(gx, gy),
-                                    Vec2::new(
-                                        gx + metrics.width as f32,
-                                        gy + metrics.height as f32,
-                                    ),
-                                ),
-                                atlas.texture_area(texture),
-                                [1., 1., 1.],
-                            );
-
-                            x += metrics.advance_width;
-                        }
-
-                        x += WS;
+        if self.nodes[node].transform.is_some() {
+            frame.pop_transform();
+        }
+        frame.set_layer(parent_layer);
+    }
+
+    fn render_node(
+        &self,
+        node: &Node,
+        frame: &mut Frame,
+        atlas: &mut FontAtlas,
+        text: &TextBook,
+        images: &ImageBook,
+    ) {
+        let background = match node.hovered {
+            true => node.hover_background.as_ref().or(node.background.as_ref()),
+            false => node.background.as_ref(),
+        };
+
+        match background {
+            Some(Background::Color(color)) => frame.rect(node.area, *color),
+            Some(Background::Gradient(gradient)) => frame.gradient(node.area, gradient),
+            None => {}
+        }
+
+        match node.kind {
+            NodeKind::Text(tid) => {
+                let mut x = node.area.0.x;
+                let mut y = node.area.0.y;
+
+                // Glyphs are rasterized in physical pixels
+                let scale = atlas.scale();
+
+                for word in text.get(tid).split_whitespace() {
+                    let w: f32 = word.chars().map(|c| atlas.size(c, PX).x).sum();
+                    if x + w + WS > node.area.1.x {
+                        x = node.area.0.x;
+                        y += LH;
                     }
+
+                    for c in word.chars() {
+                        let texture = atlas.get(c, PX);
+                        let metrics = atlas.metrics(texture);
+                        let w = metrics.width as f32 / scale;
+                        let h = metrics.height as f32 / scale;
+
+                        let gx = x + metrics.xmin as f32 / scale;
+                        let gy = y + LH - metrics.ymin as f32 / scale - h;
+                        frame.quad(
+                            atlas.texture(),
+                            Area(Vec2::new(gx, gy), Vec2::new(gx + w, gy + h)),
+                            atlas.texture_area(texture),
+                            Color::WHITE,
+                        );
+
+                        x += metrics.advance_width / scale;
+                    }
+
+                    x += WS;
                 }
-                _ => {}
             }
+            NodeKind::Image(iid, fit) => {
+                if let Some((area, tex)) = fit_image(fit, node.area, images.size(iid)) {
+                    frame.quad(images.texture(iid), area, tex, Color::WHITE);
+                }
+            }
+            _ => {}
         }
     }
 
-    fn layout(&mut self, node: usize, area: Area, atlas: &mut FontAtlas, text: &TextBook) -> Vec2 {
+    fn layout(
+        &mut self,
+        node: usize,
+        area: Area,
+        atlas: &mut FontAtlas,
+        text: &TextBook,
+        images: &ImageBook,
+    ) -> Vec2 {
         match self.nodes[node].kind {
             NodeKind::None => {
                 unreachable!()
             }
             NodeKind::Clickable(_) => {
                 let child = self.nodes[node].child;
-                let size = self.layout(child, area, atlas, text);
+                let size = self.layout(child, area, atlas, text, images);
 
                 // Set area
                 self.nodes[child].area.0.x = area.0.x;
@@ -221,6 +526,11 @@ impl Tree {
                 return size;
             }
             NodeKind::Pad(padding) => {
+                // Without children there is only the padding, and no gap to take off
+                if self.nodes[node].child == usize::MAX {
+                    return Vec2::new(area.w(), padding * 2.);
+                }
+
                 let mut child_area = Area(
                     Vec2::new(area.0.x + padding, area.0.y + padding),
                     Vec2::new(area.1.x - padding, f32::MAX),
@@ -228,7 +538,7 @@ impl Tree {
 
                 let mut child = self.nodes[node].child;
                 while child != usize::MAX {
-                    let size = self.layout(child, child_area, atlas, text);
+                    let size = self.layout(child, child_area, atlas, text, images);
 
                     // Set area
                     self.nodes[child].area.0.x = child_area.0.x;
@@ -247,8 +557,8 @@ impl Tree {
                     child = self.nodes[child].next;
                 }
 
-                // Scroll should just take up the whole area
-                return Vec2::new(area.w(), child_area.0.y + padding * 2.);
+                // Down to the last child, without the gap after it, and the padding
+                return Vec2::new(area.w(), child_area.0.y - LH - area.0.y + padding);
             }
             NodeKind::Scroll(scroll) => {
                 let child_area = Area(
@@ -260,7 +570,7 @@ impl Tree {
                 let mut child = self.nodes[node].child;
                 let mut y = child_area.0.y;
                 while child != usize::MAX {
-                    let size = self.layout(child, child_area, atlas, text);
+                    let size = self.layout(child, child_area, atlas, text, images);
 
                     // Set area
                     self.nodes[child].area.0.x = child_area.0.x;
@@ -275,14 +585,33 @@ impl Tree {
                     child = self.nodes[child].next;
                 }
 
+                // Don't scroll past either end of the content
+                let content = y - child_area.0.y - LH;
+                let max = (content - area.h()).max(0.);
+                self.nodes[node].scroll_max = max;
+                if let Some(target) = &mut self.nodes[node].scroll_target {
+                    *target = target.clamp(0., max);
+                }
+
+                // Lay it out again where it should have been
+                let clamped = scroll.clamp(0., max);
+                if clamped != scroll {
+                    self.nodes[node].kind = NodeKind::Scroll(clamped);
+                    return self.layout(node, area, atlas, text, images);
+                }
+
                 // Scroll should just take up the whole area
                 return area.size();
             }
             NodeKind::Text(tid) => {
-                // Just remeber the cache
-                if self.nodes[node].area.w() == area.w() {
+                // Just remeber the cache, unless the glyphs need rasterizing
+                // at a new size, which has to happen before the atlas is built
+                if self.nodes[node].area.w() == area.w()
+                    && self.nodes[node].text_scale == atlas.scale()
+                {
                     return self.nodes[node].area.size();
                 }
+                self.nodes[node].text_scale = atlas.scale();
 
                 let mut h = LH;
 
@@ -300,6 +629,61 @@ impl Tree {
 
                 return Vec2::new(area.w(), h);
             }
+            NodeKind::Image(iid, _) => {
+                // Take up the whole width, but only as much height as the
+                // image needs, shrinking it if it is too wide
+                let size = images.size(iid);
+
+                // An empty image takes up no room
+                if size.x <= 0. || size.y <= 0. {
+                    return Vec2::new(area.w(), 0.);
+                }
+                let scale = (area.w() / size.x).min(1.);
+
+                return Vec2::new(area.w(), size.y * scale);
+            }
         }
     }
 }
+
+/// Where to draw an image of the given size in the area, and which part of
+/// it to draw. Images are centered, and cropped to the area. None if there
+/// is nothing to draw, because the image or the area is empty.
+fn fit_image(fit: Fit, area: Area, size: Vec2) -> Option<(Area, Area)> {
+    if size.x <= 0. || size.y <= 0. || area.w() <= 0. || area.h() <= 0. {
+        return None;
+    }
+
+    let scale = match fit {
+        Fit::Contain => Vec2::new(1., 1.) * (area.w() / size.x).min(area.h() / size.y),
+        Fit::Cover => Vec2::new(1., 1.) * (area.w() / size.x).max(area.h() / size.y),
+        Fit::Fill => Vec2::new(area.w() / size.x, area.h() / size.y),
+        Fit::None => Vec2::new(1., 1.),
+    };
+
+    // Centered in the area
+    let w = size.x * scale.x;
+    let h = size.y * scale.y;
+    let x = area.0.x + (area.w() - w) / 2.;
+    let y = area.0.y + (area.h() - h) / 2.;
+    let image = Area(Vec2::new(x, y), Vec2::new(x + w, y + h));
+
+    // Cut off whatever sticks out, along with that part of the texture
+    let shown = image.intersect(area);
+    let tex = Area(
+        Vec2::new((shown.0.x - x) / w, (shown.0.y - y) / h),
+        Vec2::new((shown.1.x - x) / w, (shown.1.y - y) / h),
+    );
+
+    return Some((shown, tex));
+}
+
+// Of two nodes and the layers they are on, the one drawn on top. The first
+// one if they are on the same layer
+fn topmost(a: Option<(usize, i32)>, b: Option<(usize, i32)>) -> Option<(usize, i32)> {
+    return match (a, b) {
+        (Some(a), Some(b)) if b.1 > a.1 => Some(b),
+        (None, b) => b,
+        (a, _) => a,
+    };
+}
@@ -1,18 +1,25 @@
 mod layout;
 mod ruglet;
 
+#[cfg(test)]
+mod snapshot;
+
 use std::{fs, time::Instant};
 
 use layout::*;
 use ruglet::*;
-use winit::event::MouseButton;
+use winit::{dpi::PhysicalSize, event::MouseButton};
 
 struct MyApp {
     tree: Tree,
     root: usize,
     font: FontAtlas,
     text: TextBook,
+    images: ImageBook,
     link: Vec<String>,
+
+    // When the last frame was drawn, to know how far to animate
+    drawn: Instant,
 }
 
 impl MyApp {
@@ -20,8 +27,13 @@ impl MyApp {
         let font = FontAtlas::new();
         let mut tree = Tree::new();
         let mut text = TextBook::new();
+        let mut images = ImageBook::new();
         let mut link = vec![];
 
+        // Our title goes above the chapters
+        let title = TextureData::decode(include_bytes!("../res/title.png")).unwrap();
+        let title = tree.add(NodeKind::Image(images.add(title), Fit::Contain), vec![]);
+
         let chapters = fs::read_dir(dir)
             .unwrap()
             .map(|file| file.unwrap())
@@ -35,11 +47,14 @@ impl MyApp {
 
                 // make it clickable
                 link.push(path);
-                return tree.add(NodeKind::Clickable(link.len() - 1), vec![text]);
+                let link = tree.add(NodeKind::Clickable(link.len() - 1), vec![text]);
+                tree.set_background(link, Color::hex(0x282828));
+                tree.set_hover_background(link, Color::hex(0x3c3c3c));
+                return link;
             })
-            .collect();
+            .collect::<Vec<_>>();
 
-        let pad = tree.add(NodeKind::Pad(50.0), chapters);
+        let pad = tree.add(NodeKind::Pad(25.0), [vec![title], chapters].concat());
 
         let root = tree.add(NodeKind::Scroll(0.0), vec![pad]);
 
@@ -50,7 +65,9 @@ impl MyApp {
             root,
             font,
             text,
+            images,
             link,
+            drawn: Instant::now(),
         };
     }
 
@@ -66,7 +83,16 @@ impl MyApp {
             })
             .collect();
 
-        let pad = self.tree.add(NodeKind::Pad(50.0), paragraphs);
+        let pad = self.tree.add(NodeKind::Pad(25.0), paragraphs);
+
+        // Shade the edges of the page a little
+        let edge = Color::hex(0x161616);
+        let page = Gradient::linear(
+            0.,
+            &[(0., edge), (0.04, Color::BLACK), (0.96, Color::BLACK), (1., edge)],
+        );
+        self.tree.set_background(pad, page);
+
         let root = self.tree.add(NodeKind::Scroll(0.0), vec![pad]);
 
         self.tree.replace(self.root, root);
@@ -82,28 +108,72 @@ impl<'a> Application for MyApp {
         }
     }
 
+    fn on_mouse_move(&mut self, mouse: Vec2) -> bool {
+        return self.tree.hover(self.root, Some(mouse));
+    }
+
+    fn on_mouse_leave(&mut self) {
+        self.tree.hover(self.root, None);
+    }
+
     fn on_mouse_scroll(&mut self, _dx: f32, dy: f32) {
-        if let NodeKind::Scroll(scroll) = self.tree.get(self.root) {
-            self.tree.update(self.root, NodeKind::Scroll(scroll - dy));
-        }
+        self.tree.scroll_by(self.root, -dy);
+    }
+
+    fn line_height(&self) -> f32 {
+        return LH;
     }
 
     fn on_draw(&mut self, frame: &mut Frame) {
         let now = Instant::now();
 
+        frame.set_clear_color(Color::BLACK);
+
+        // Keep drawing until we're done scrolling
+        if self.tree.animate(now - self.drawn) {
+            frame.request_redraw();
+        }
+        self.drawn = now;
+
         // Render the dom
-        self.tree
-            .build(self.root, frame, &mut self.font, &self.text);
+        self.tree.build(
+            self.root,
+            frame,
+            &mut self.font,
+            &self.text,
+            &mut self.images,
+        );
 
         let elapsed = now.elapsed();
-        println!("Elapsed: {:.2?}", elapsed);
+        println!(
+            "Elapsed: {:.2?} Uploaded: {}B",
+            elapsed,
+            frame.upload_size()
+        );
+    }
+
+    fn samples(&self) -> u32 {
+        return 4;
     }
 }
 
 fn main() {
     let mut app = MyApp::new("/Users/felixmoses/Library/Containers/com.apple.BKAgentService/Data/Documents/iBooks/Books/2A2BC927A5ECC63CC1566A50E4DB8941.epub/OEBPS");
 
+    // `gpu --png out.png` renders one frame to a file instead of opening a window
+    let args: Vec<String> = std::env::args().collect();
+    if let [_, flag, path] = args.as_slice() {
+        if flag == "--png" {
+            let size = PhysicalSize::new(1280, 720);
+            match pollster::block_on(app.render_to_image(size, 2.)) {
+                Ok(image) => image.save_png(path).unwrap(),
+                Err(e) => eprintln!("Render: {}", e),
+            }
+            return;
+        }
+    }
+
     if let Err(e) = pollster::block_on(app.run()) {
-        eprintln!("Render: {:?}", e);
+        eprintln!("Render: {}", e);
     }
 }
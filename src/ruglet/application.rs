@@ -1,35 +1,115 @@
 use crate::ruglet::Vec2;
 
-use super::{renderer::Renderer, Frame};
-use winit::{error::EventLoopError, event::*, event_loop::*, window::WindowBuilder};
+use std::time::Instant;
+
+use super::{
+    framelog::FrameLog, renderer::Renderer, Frame, Headless, RugletError, Software, TextureData,
+};
+use winit::{
+    dpi::PhysicalSize,
+    event::{Ime as InputMethod, *},
+    event_loop::*,
+    keyboard::{Key, ModifiersState},
+    window::WindowBuilder,
+};
+
+/// Text typed into the window, see `Application::on_text_input`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextInput {
+    /// Finished characters, ready to be inserted
+    Commit(String),
+
+    /// What an input method is still composing, replacing the last preedit.
+    /// Empty once it's done or cancelled. The cursor is a byte range into it
+    Preedit(String, Option<(usize, usize)>),
+}
 
 pub trait Application {
     // Event functions
     fn on_mouse_scroll(&mut self, _dx: f32, _dy: f32) {}
     fn on_press(&mut self, _mouse: Vec2, _button: MouseButton) {}
 
+    /// Return true if something changed, and the window needs to be drawn
+    /// again. Moving the mouse around is too common to always do that.
+    fn on_mouse_move(&mut self, _mouse: Vec2) -> bool {
+        return false;
+    }
+    fn on_mouse_enter(&mut self) {}
+    fn on_mouse_leave(&mut self) {}
+
+    /// How far a mouse wheel scrolls for each notch, in logical pixels.
+    fn line_height(&self) -> f32 {
+        return 40.;
+    }
+
+    /// Called again while the key is held down, as it repeats.
+    fn on_key_down(&mut self, _key: &Key, _modifiers: ModifiersState) {}
+    fn on_key_up(&mut self, _key: &Key, _modifiers: ModifiersState) {}
+
+    /// Typed characters, from the keyboard or an input method.
+    fn on_text_input(&mut self, _text: &TextInput) {}
+
     // Handling
     fn on_draw(&mut self, frame: &mut Frame);
 
-    async fn run(&mut self) -> Result<(), EventLoopError> {
+    /// How many samples per pixel the window is drawn with, see `Renderer::new`.
+    fn samples(&self) -> u32 {
+        return 1;
+    }
+
+    /// Draws a single frame offscreen, without opening a window, as if on
+    /// a display with that scale factor. Falls back to the CPU rasterizer
+    /// if wgpu has no adapter.
+    async fn render_to_image(
+        &mut self,
+        size: PhysicalSize<u32>,
+        scale: f32,
+    ) -> Result<TextureData, RugletError> {
+        let mut frame = Frame::with_scale(size, scale);
+        self.on_draw(&mut frame);
+
+        return match Headless::new(size).await {
+            Ok(mut headless) => headless.render(frame),
+            Err(RugletError::NoAdapter) => Ok(Software::new(size).render(frame)),
+            Err(err) => Err(err),
+        };
+    }
+
+    async fn run(&mut self) -> Result<(), RugletError> {
         // Initialize the window and event handler
-        let event_loop = EventLoop::new().unwrap();
-        let window = WindowBuilder::new().build(&event_loop).unwrap();
+        let event_loop = EventLoop::new()?;
+        let window = WindowBuilder::new().build(&event_loop)?;
         window.set_title("read");
+        window.set_ime_allowed(true);
 
         // Manage our wgpu stuff
-        let mut renderer = Renderer::new(&window).await;
+        let mut renderer = Renderer::new(&window, self.samples()).await?;
         renderer.resize(window.inner_size());
 
         // Don't update if we don't have to
         event_loop.set_control_flow(ControlFlow::Wait);
 
+        // Everything but the renderer works in logical pixels
+        let mut scale = window.scale_factor() as f32;
+
         // Where is the mouse?
         let mut mouse = Vec2::zero();
 
+        // Which of shift, ctrl, alt and super are held down?
+        let mut modifiers = ModifiersState::empty();
+
+        // Keys type nothing while an input method is composing
+        let mut composing = false;
+
+        // Off unless RUGLET_FRAME_LOG is set
+        let mut log = FrameLog::from_env();
+
+        // Drawing can't return errors out of the event loop, so they wait here
+        let mut failed = None;
+
         // Handle events as they come in
         use WindowEvent::*;
-        return event_loop.run(move |event, control_flow| match event {
+        event_loop.run(|event, control_flow| match event {
             Event::WindowEvent {
                 ref event,
                 window_id,
@@ -37,19 +117,44 @@ pub trait Application {
                 // Handle window events
                 CloseRequested => control_flow.exit(),
                 Resized(physical_size) => {
+                    // Draw before returning, so the window system never has to
+                    // stretch the last frame to the new size while it waits
                     renderer.resize(*physical_size);
+                    if renderer.size == *physical_size {
+                        log.resized(*physical_size);
+                        if let Err(err) = draw(self, &mut renderer, scale, &mut log, "resize") {
+                            failed = Some(err);
+                            control_flow.exit();
+                        }
+                    }
+                }
+                ScaleFactorChanged { scale_factor, .. } => {
+                    // The new size comes along in a `Resized` of its own
+                    scale = *scale_factor as f32;
                     renderer.window().request_redraw();
                 }
                 RedrawRequested => {
-                    let mut frame = Frame::new(renderer.size);
-                    self.on_draw(&mut frame);
-                    renderer.render(frame).unwrap();
+                    if let Err(err) = draw(self, &mut renderer, scale, &mut log, "redraw") {
+                        failed = Some(err);
+                        control_flow.exit();
+                    }
                 }
 
                 // Handle user inputs
                 CursorMoved { position, .. } => {
-                    mouse.x = position.x as f32;
-                    mouse.y = position.y as f32;
+                    mouse.x = position.x as f32 / scale;
+                    mouse.y = position.y as f32 / scale;
+                    if self.on_mouse_move(mouse) {
+                        renderer.window().request_redraw();
+                    }
+                }
+                CursorEntered { .. } => {
+                    self.on_mouse_enter();
+                    renderer.window().request_redraw();
+                }
+                CursorLeft { .. } => {
+                    self.on_mouse_leave();
+                    renderer.window().request_redraw();
                 }
                 MouseInput { state, button, .. } => {
                     if *state == ElementState::Released {
@@ -58,18 +163,92 @@ pub trait Application {
                     }
                 }
 
-                MouseWheel { delta, .. } => match delta {
-                    MouseScrollDelta::PixelDelta(pos) => {
-                        self.on_mouse_scroll(pos.x as f32, pos.y as f32);
-                        renderer.window.request_redraw();
+                ModifiersChanged(new) => modifiers = new.state(),
+                KeyboardInput { event, .. } => {
+                    match event.state {
+                        ElementState::Pressed => self.on_key_down(&event.logical_key, modifiers),
+                        ElementState::Released => self.on_key_up(&event.logical_key, modifiers),
+                    }
+
+                    // Control characters come through as keys only
+                    if let Some(text) = &event.text {
+                        let printable = text.chars().all(|c| !c.is_control());
+                        if event.state == ElementState::Pressed && printable && !composing {
+                            self.on_text_input(&TextInput::Commit(text.to_string()));
+                        }
+                    }
+                    renderer.window().request_redraw();
+                }
+                Ime(ime) => {
+                    // Turning the input method off drops whatever it was composing
+                    let input = match ime {
+                        InputMethod::Preedit(text, cursor) => {
+                            TextInput::Preedit(text.clone(), *cursor)
+                        }
+                        InputMethod::Commit(text) => TextInput::Commit(text.clone()),
+                        InputMethod::Enabled | InputMethod::Disabled => {
+                            TextInput::Preedit(String::new(), None)
+                        }
+                    };
+                    composing = matches!(&input, TextInput::Preedit(text, _) if !text.is_empty());
+
+                    self.on_text_input(&input);
+                    renderer.window().request_redraw();
+                }
+
+                MouseWheel { delta, .. } => {
+                    // Trackpads scroll by pixels, and mouse wheels by lines
+                    match delta {
+                        MouseScrollDelta::PixelDelta(pos) => {
+                            self.on_mouse_scroll(pos.x as f32 / scale, pos.y as f32 / scale);
+                        }
+                        MouseScrollDelta::LineDelta(x, y) => {
+                            let line = self.line_height();
+                            self.on_mouse_scroll(x * line, y * line);
+                        }
                     }
-                    _ => println!("Unsupported scroll type!"),
-                },
+                    renderer.window.request_redraw();
+                }
 
                 // We don't care about the rest
                 _ => {}
             },
             _ => {}
-        });
+        })?;
+
+        return match failed {
+            Some(err) => Err(err),
+            None => Ok(()),
+        };
+    }
+}
+
+fn draw<A: Application + ?Sized>(
+    app: &mut A,
+    renderer: &mut Renderer,
+    scale: f32,
+    log: &mut FrameLog,
+    cause: &str,
+) -> Result<(), RugletError> {
+    let start = Instant::now();
+
+    let mut frame = Frame::with_scale(renderer.size, scale);
+    app.on_draw(&mut frame);
+
+    // Checked here, since rendering uses up the frame
+    if frame.redraw_requested() {
+        renderer.window().request_redraw();
     }
+
+    match renderer.render(frame)? {
+        true => log.presented(
+            cause,
+            renderer.size,
+            renderer.window().inner_size(),
+            start.elapsed(),
+        ),
+        false => log.skipped(cause, renderer.size),
+    }
+
+    return Ok(());
 }
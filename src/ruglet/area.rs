@@ -1,6 +1,7 @@
 use std::fmt::Debug;
+use std::ops::{Add, Mul, Neg, Sub};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Vec2 {
     pub x: f32,
     pub y: f32,
@@ -15,6 +16,32 @@ impl Vec2 {
         return Vec2 { x, y };
     }
 
+    pub fn dot(&self, other: Vec2) -> f32 {
+        return self.x * other.x + self.y * other.y;
+    }
+
+    pub fn cross(&self, other: Vec2) -> f32 {
+        return self.x * other.y - self.y * other.x;
+    }
+
+    pub fn length(&self) -> f32 {
+        return self.x.hypot(self.y);
+    }
+
+    pub fn normalize(&self) -> Vec2 {
+        return *self * (1. / self.length());
+    }
+
+    /// Rotated a quarter turn, clockwise on screen.
+    pub fn perp(&self) -> Vec2 {
+        return Vec2::new(-self.y, self.x);
+    }
+
+    pub fn rotate(&self, angle: f32) -> Vec2 {
+        let (sin, cos) = angle.sin_cos();
+        return Vec2::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos);
+    }
+
     pub fn inside(&self, area: Area) -> bool {
         return area.0.x <= self.x
             && self.x <= area.1.x
@@ -23,7 +50,39 @@ impl Vec2 {
     }
 }
 
-#[derive(Clone, Copy)]
+impl Add for Vec2 {
+    type Output = Vec2;
+
+    fn add(self, other: Vec2) -> Vec2 {
+        return Vec2::new(self.x + other.x, self.y + other.y);
+    }
+}
+
+impl Sub for Vec2 {
+    type Output = Vec2;
+
+    fn sub(self, other: Vec2) -> Vec2 {
+        return Vec2::new(self.x - other.x, self.y - other.y);
+    }
+}
+
+impl Mul<f32> for Vec2 {
+    type Output = Vec2;
+
+    fn mul(self, scale: f32) -> Vec2 {
+        return Vec2::new(self.x * scale, self.y * scale);
+    }
+}
+
+impl Neg for Vec2 {
+    type Output = Vec2;
+
+    fn neg(self) -> Vec2 {
+        return Vec2::new(-self.x, -self.y);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
 pub struct Area(pub Vec2, pub Vec2);
 
 impl Debug for Area {
@@ -52,6 +111,15 @@ impl Area {
                 && target.0.y < self.1.y);
     }
 
+    /// The part of both areas that overlaps, which might be empty.
+    pub fn intersect(&self, other: Area) -> Area {
+        let min = Vec2::new(self.0.x.max(other.0.x), self.0.y.max(other.0.y));
+        let max = Vec2::new(self.1.x.min(other.1.x), self.1.y.min(other.1.y));
+
+        // Keep empty areas from turning inside out
+        return Area(min, Vec2::new(max.x.max(min.x), max.y.max(min.y)));
+    }
+
     pub fn w(&self) -> f32 {
         return self.1.x - self.0.x;
     }
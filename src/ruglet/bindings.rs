@@ -9,41 +9,36 @@ pub struct Binding {
     pub group: BindGroup,
 }
 
-const SCREEN_SIZE_BIND_GROUP: usize = 0;
-const TEXTURE_BIND_GROUP: usize = 1;
+pub const TEXTURE_BIND_GROUP: usize = 1;
+pub const STORAGE_BIND_GROUP: usize = 2;
 
-pub type Bindings = [Binding; 2];
+pub type Bindings = [Binding; 3];
 
-pub fn create_bindings(device: &Device, queue: &Queue) -> Bindings {
+pub fn create_bindings(
+    device: &Device,
+    queue: &Queue,
+    screen_size: &Buffer,
+    stops: &Buffer,
+    styles: &Buffer,
+) -> Bindings {
     return [
-        create_screen_size_bindgroup(device),
+        create_screen_size_bindgroup(device, screen_size),
         create_texture_bindgroup(device, queue),
+        create_storage_bindgroup(device, stops, styles),
     ];
 }
 
-pub fn update_screen_size_bindgroup(
-    device: &Device,
-    bindings: &mut Bindings,
-    new_size: PhysicalSize<u32>,
-) {
-    let screen_size_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: None,
-        contents: bytemuck::cast_slice(&[ScreenSizeUniform::new(new_size.width, new_size.height)]),
-        usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-    });
-
-    bindings[SCREEN_SIZE_BIND_GROUP].group = device.create_bind_group(&BindGroupDescriptor {
-        layout: &bindings[SCREEN_SIZE_BIND_GROUP].layout,
-        entries: &[BindGroupEntry {
-            binding: 0,
-            resource: screen_size_buffer.as_entire_binding(),
-        }],
-        label: None,
-    });
+pub fn update_screen_size(queue: &Queue, buffer: &Buffer, new_size: PhysicalSize<u32>) {
+    queue.write_buffer(
+        buffer,
+        0,
+        bytemuck::cast_slice(&[ScreenSizeUniform::new(new_size.width, new_size.height)]),
+    );
 }
 
-pub fn update_texture_bindgroup(device: &Device, bindings: &mut Bindings, texture: Texture) {
-    bindings[TEXTURE_BIND_GROUP].group = device.create_bind_group(&BindGroupDescriptor {
+/// A bind group for the texture, to swap in for the blank one.
+pub fn create_texture_group(device: &Device, bindings: &Bindings, texture: &Texture) -> BindGroup {
+    return device.create_bind_group(&BindGroupDescriptor {
         label: Some("texture_bind_group"),
         layout: &bindings[TEXTURE_BIND_GROUP].layout,
         entries: &[
@@ -56,12 +51,12 @@ pub fn update_texture_bindgroup(device: &Device, bindings: &mut Bindings, textur
                 resource: BindingResource::Sampler(&texture.sampler),
             },
         ],
-    })
+    });
 }
 
 pub fn create_texture_bindgroup(device: &Device, queue: &Queue) -> Binding {
     // Create blank 1x1px texture
-    let texture = create_texture(device, queue, TextureData::blank());
+    let texture = create_texture(device, queue, &TextureData::blank());
 
     let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
         entries: &[
@@ -106,18 +101,80 @@ pub fn create_texture_bindgroup(device: &Device, queue: &Queue) -> Binding {
     };
 }
 
-fn create_screen_size_bindgroup(device: &Device) -> Binding {
+/// Points the bind group at new storage buffers, after one had to grow.
+pub fn update_storage_bindgroup(
+    device: &Device,
+    bindings: &mut Bindings,
+    stops: &Buffer,
+    styles: &Buffer,
+) {
+    let layout = &bindings[STORAGE_BIND_GROUP].layout;
+    bindings[STORAGE_BIND_GROUP].group = create_storage_group(device, layout, stops, styles);
+}
+
+fn create_storage_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    stops: &Buffer,
+    styles: &Buffer,
+) -> BindGroup {
+    return device.create_bind_group(&BindGroupDescriptor {
+        label: Some("storage_bind_group"),
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: stops.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: styles.as_entire_binding(),
+            },
+        ],
+    });
+}
+
+// The gradient stops, then the quad styles
+fn create_storage_bindgroup(device: &Device, stops: &Buffer, styles: &Buffer) -> Binding {
+    let entry = |binding, visibility| BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    };
+
+    let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some("storage_bind_group_layout"),
+        entries: &[
+            entry(0, ShaderStages::FRAGMENT),
+            entry(1, ShaderStages::VERTEX),
+        ],
+    });
+
+    return Binding {
+        group: create_storage_group(device, &layout, stops, styles),
+        layout,
+    };
+}
+
+pub fn create_screen_size_buffer(device: &Device) -> Buffer {
     let screen_size = ScreenSizeUniform {
         width: 1.,
         height: 1.,
     };
 
-    let screen_size_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+    return device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
         label: Some("Screen size Buffer"),
         contents: bytemuck::cast_slice(&[screen_size]),
         usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
     });
+}
 
+fn create_screen_size_bindgroup(device: &Device, screen_size_buffer: &Buffer) -> Binding {
     let screen_size_bind_group_layout =
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("screen_size_bind_group_layout"),
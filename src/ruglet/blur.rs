@@ -0,0 +1,287 @@
+use wgpu::util::DeviceExt;
+use wgpu::*;
+use winit::dpi::PhysicalSize;
+
+use super::texture::Texture;
+
+/// How far the blur reaches, and how much each pixel along the way counts.
+/// Past three standard deviations there's too little left to see.
+pub fn blur_kernel(sigma: f32) -> (i32, Vec<f32>) {
+    let radius = (3. * sigma).ceil().max(0.) as i32;
+    let sigma = sigma.max(0.0001);
+
+    let weights: Vec<f32> = (-radius..=radius)
+        .map(|i| (-(i * i) as f32 / (2. * sigma * sigma)).exp())
+        .collect();
+    let total: f32 = weights.iter().sum();
+
+    return (radius, weights.iter().map(|w| w / total).collect());
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct BlurParams {
+    direction: [i32; 2],
+    radius: i32,
+    sigma: f32,
+}
+
+// Only needed once a frame has a backdrop in it
+struct Targets {
+    scene: TextureView,
+    horizontal: TextureView,
+    blurred: Texture,
+}
+
+/// Blurs what has been drawn so far, for backdrops. Frames with backdrops
+/// are drawn into `scene` and copied to the screen at the end.
+pub struct Blur {
+    format: TextureFormat,
+    pipeline: RenderPipeline,
+    layout: BindGroupLayout,
+    size: PhysicalSize<u32>,
+    targets: Option<Targets>,
+}
+
+impl Blur {
+    pub fn new(device: &Device, format: TextureFormat) -> Blur {
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("blur_bind_group_layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    // Read with textureLoad, so there's no sampler
+                    ty: BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: TextureViewDimension::D2,
+                        sample_type: TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        return Blur {
+            format,
+            pipeline: create_blur_pipeline(device, format, &layout),
+            layout,
+            size: PhysicalSize::new(1, 1),
+            targets: None,
+        };
+    }
+
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        // Recreated at the new size when they're next needed
+        self.size = size;
+        self.targets = None;
+    }
+
+    /// Creates the textures, if they haven't been already.
+    pub fn prepare(&mut self, device: &Device) {
+        let (format, size) = (self.format, self.size);
+        self.targets.get_or_insert_with(|| Targets {
+            scene: create_target(device, format, size).create_view(&Default::default()),
+            horizontal: create_target(device, format, size).create_view(&Default::default()),
+            blurred: {
+                let texture = create_target(device, format, size);
+                Texture {
+                    view: texture.create_view(&Default::default()),
+                    sampler: device.create_sampler(&SamplerDescriptor::default()),
+                }
+            },
+        });
+    }
+
+    /// Where to draw a frame that has backdrops in it.
+    pub fn scene(&self) -> &TextureView {
+        return &self.targets.as_ref().unwrap().scene;
+    }
+
+    /// Blurs the scene under the rect, and returns the blurred copy.
+    pub fn blur(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        rect: [f32; 4],
+        sigma: f32,
+    ) -> &Texture {
+        let targets = self.targets.as_ref().unwrap();
+        let (radius, _) = blur_kernel(sigma);
+
+        // Only blur under the rect, along with its anti-aliased edge
+        let (w, h) = (self.size.width as i32, self.size.height as i32);
+        let x0 = ((rect[0] - 1.).floor() as i32).clamp(0, w);
+        let y0 = ((rect[1] - 1.).floor() as i32).clamp(0, h);
+        let x1 = ((rect[2] + 1.).ceil() as i32).clamp(0, w);
+        let y1 = ((rect[3] + 1.).ceil() as i32).clamp(0, h);
+
+        // Across, including the rows the second pass reaches
+        let rows = [(y0 - radius).max(0), (y1 + radius).min(h)];
+        let params = BlurParams {
+            direction: [1, 0],
+            radius,
+            sigma,
+        };
+        self.pass(
+            device,
+            encoder,
+            &targets.scene,
+            &targets.horizontal,
+            params,
+            [x0, rows[0], x1, rows[1]],
+        );
+
+        // Then down
+        let params = BlurParams {
+            direction: [0, 1],
+            ..params
+        };
+        self.pass(
+            device,
+            encoder,
+            &targets.horizontal,
+            &targets.blurred.view,
+            params,
+            [x0, y0, x1, y1],
+        );
+
+        return &targets.blurred;
+    }
+
+    /// Copies the scene to where the frame was meant to go.
+    pub fn copy(&self, device: &Device, encoder: &mut CommandEncoder, view: &TextureView) {
+        let targets = self.targets.as_ref().unwrap();
+        let params = BlurParams {
+            direction: [0, 0],
+            radius: 0,
+            sigma: 0.,
+        };
+        let (w, h) = (self.size.width as i32, self.size.height as i32);
+
+        self.pass(device, encoder, &targets.scene, view, params, [0, 0, w, h]);
+    }
+
+    fn pass(
+        &self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        source: &TextureView,
+        target: &TextureView,
+        params: BlurParams,
+        [x0, y0, x1, y1]: [i32; 4],
+    ) {
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+
+        let buffer = device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("Blur Params Buffer"),
+            contents: bytemuck::cast_slice(&[params]),
+            usage: BufferUsages::UNIFORM,
+        });
+        let group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("blur_bind_group"),
+            layout: &self.layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(source),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        // Only the scissor is drawn over, the rest is kept
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Blur Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &group, &[]);
+        render_pass.set_scissor_rect(x0 as u32, y0 as u32, (x1 - x0) as u32, (y1 - y0) as u32);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_target(device: &Device, format: TextureFormat, size: PhysicalSize<u32>) -> wgpu::Texture {
+    return device.create_texture(&TextureDescriptor {
+        label: Some("blur_target"),
+        size: Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+}
+
+fn create_blur_pipeline(
+    device: &Device,
+    format: TextureFormat,
+    layout: &BindGroupLayout,
+) -> RenderPipeline {
+    let shader = device.create_shader_module(include_wgsl!("blur.wgsl"));
+
+    let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some("Blur Pipeline Layout"),
+        bind_group_layouts: &[layout],
+        push_constant_ranges: &[],
+    });
+
+    return device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some("Blur Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: PipelineCompilationOptions::default(),
+        },
+        fragment: Some(FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            // Replaces what's there, blurred pixels are already blended
+            targets: &[Some(ColorTargetState {
+                format,
+                blend: None,
+                write_mask: ColorWrites::ALL,
+            })],
+            compilation_options: PipelineCompilationOptions::default(),
+        }),
+        primitive: PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    });
+}
@@ -0,0 +1,40 @@
+// Separable gaussian blur, one direction at a time.
+// With a radius of 0 it just copies the texture.
+
+struct Params {
+    direction: vec2<i32>,
+    radius: i32,
+    sigma: f32,
+};
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+@group(0) @binding(1)
+var<uniform> params: Params;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // One triangle that covers the whole screen
+    let p = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(p * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let last = vec2<i32>(textureDimensions(source)) - 1;
+    let p = vec2<i32>(position.xy);
+    let sigma = max(params.sigma, 0.0001);
+
+    // Pixels past the edge repeat the edge
+    var sum = vec4<f32>(0.0);
+    var total = 0.0;
+    for (var i = -params.radius; i <= params.radius; i++) {
+        let weight = exp(-f32(i * i) / (2.0 * sigma * sigma));
+        let texel = textureLoad(source, clamp(p + params.direction * i, vec2<i32>(0), last), 0);
+
+        sum += texel * weight;
+        total += weight;
+    }
+
+    return sum / total;
+}
@@ -0,0 +1,68 @@
+use wgpu::*;
+
+// Enough for a screen full of text before we ever need to grow
+const INITIAL_CAPACITY: BufferAddress = 1 << 16;
+
+/// A GPU buffer that is kept between frames and rewritten in place,
+/// doubling in size whenever the data outgrows it.
+pub struct GrowBuffer {
+    pub buffer: Buffer,
+    label: &'static str,
+    usage: BufferUsages,
+    capacity: BufferAddress,
+    len: BufferAddress,
+}
+
+impl GrowBuffer {
+    pub fn new(device: &Device, label: &'static str, usage: BufferUsages) -> GrowBuffer {
+        let usage = usage | BufferUsages::COPY_DST;
+
+        return GrowBuffer {
+            buffer: create_buffer(device, label, usage, INITIAL_CAPACITY),
+            label,
+            usage,
+            capacity: INITIAL_CAPACITY,
+            len: 0,
+        };
+    }
+
+    /// Uploads the data, replacing whatever was there before.
+    /// The length has to be a multiple of `COPY_BUFFER_ALIGNMENT`.
+    /// Returns true if the buffer had to be replaced to make room, so
+    /// anything bound to the old one needs updating.
+    pub fn write(&mut self, device: &Device, queue: &Queue, data: &[u8]) -> bool {
+        self.len = data.len() as BufferAddress;
+
+        // Make room if we need to
+        let grown = self.len > self.capacity;
+        if grown {
+            while self.capacity < self.len {
+                self.capacity *= 2;
+            }
+
+            self.buffer = create_buffer(device, self.label, self.usage, self.capacity);
+        }
+
+        queue.write_buffer(&self.buffer, 0, data);
+        return grown;
+    }
+
+    /// The part of the buffer that was last written.
+    pub fn slice(&self) -> BufferSlice<'_> {
+        return self.buffer.slice(..self.len);
+    }
+}
+
+fn create_buffer(
+    device: &Device,
+    label: &'static str,
+    usage: BufferUsages,
+    size: BufferAddress,
+) -> Buffer {
+    return device.create_buffer(&BufferDescriptor {
+        label: Some(label),
+        size,
+        usage,
+        mapped_at_creation: false,
+    });
+}
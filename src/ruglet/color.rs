@@ -0,0 +1,65 @@
+/// A color in linear RGB, which is what the shader blends in, with straight
+/// (not premultiplied) alpha. Most colors are picked in sRGB, so use `srgb`
+/// or `hex` for those.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const TRANSPARENT: Color = Color::linear(0., 0., 0., 0.);
+    pub const BLACK: Color = Color::linear(0., 0., 0., 1.);
+    pub const WHITE: Color = Color::linear(1., 1., 1., 1.);
+
+    pub const fn linear(r: f32, g: f32, b: f32, a: f32) -> Color {
+        return Color { r, g, b, a };
+    }
+
+    /// An opaque color from 8 bit sRGB channels.
+    pub fn srgb(r: u8, g: u8, b: u8) -> Color {
+        return Color::linear(from_srgb(r), from_srgb(g), from_srgb(b), 1.);
+    }
+
+    /// An opaque color from `0xRRGGBB`, like in CSS.
+    pub fn hex(rgb: u32) -> Color {
+        let [_, r, g, b] = rgb.to_be_bytes();
+        return Color::srgb(r, g, b);
+    }
+
+    #[allow(dead_code)]
+    pub fn with_alpha(self, a: f32) -> Color {
+        return Color { a, ..self };
+    }
+
+    pub fn to_array(self) -> [f32; 4] {
+        return [self.r, self.g, self.b, self.a];
+    }
+
+    /// The color multiplied by its alpha, which is how it gets blended.
+    pub fn premultiplied(self) -> [f32; 4] {
+        return [self.r * self.a, self.g * self.a, self.b * self.a, self.a];
+    }
+}
+
+/// Decodes an 8 bit sRGB channel to linear.
+pub fn from_srgb(c: u8) -> f32 {
+    let c = c as f32 / 255.;
+    if c <= 0.04045 {
+        return c / 12.92;
+    }
+    return ((c + 0.055) / 1.055).powf(2.4);
+}
+
+/// Encodes a linear channel to 8 bit sRGB.
+pub fn to_srgb(c: f32) -> u8 {
+    let c = c.clamp(0., 1.);
+    let c = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1. / 2.4) - 0.055
+    };
+    return (c * 255.).round() as u8;
+}
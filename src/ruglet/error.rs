@@ -0,0 +1,75 @@
+use std::{error::Error, fmt};
+
+use wgpu::{CreateSurfaceError, RequestDeviceError, SurfaceError};
+use winit::error::{EventLoopError, OsError};
+
+/// Why the window, or the offscreen target, couldn't be set up or drawn to.
+#[derive(Debug)]
+pub enum RugletError {
+    EventLoop(EventLoopError),
+    Window(OsError),
+    CreateSurface(CreateSurfaceError),
+    RequestDevice(RequestDeviceError),
+    Surface(SurfaceError),
+
+    /// wgpu found no GPU that can draw to the window, or offscreen
+    NoAdapter,
+}
+
+impl fmt::Display for RugletError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            RugletError::EventLoop(err) => write!(f, "couldn't run the event loop: {}", err),
+            RugletError::Window(err) => write!(f, "couldn't open a window: {}", err),
+            RugletError::CreateSurface(err) => {
+                write!(f, "couldn't draw to the window: {}", err)
+            }
+            RugletError::RequestDevice(err) => write!(f, "couldn't set up the GPU: {}", err),
+            RugletError::Surface(err) => write!(f, "couldn't get a frame to draw: {}", err),
+            RugletError::NoAdapter => write!(f, "no GPU to draw with"),
+        };
+    }
+}
+
+impl Error for RugletError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        return match self {
+            RugletError::EventLoop(err) => Some(err),
+            RugletError::Window(err) => Some(err),
+            RugletError::CreateSurface(err) => Some(err),
+            RugletError::RequestDevice(err) => Some(err),
+            RugletError::Surface(err) => Some(err),
+            RugletError::NoAdapter => None,
+        };
+    }
+}
+
+impl From<EventLoopError> for RugletError {
+    fn from(err: EventLoopError) -> Self {
+        return RugletError::EventLoop(err);
+    }
+}
+
+impl From<OsError> for RugletError {
+    fn from(err: OsError) -> Self {
+        return RugletError::Window(err);
+    }
+}
+
+impl From<CreateSurfaceError> for RugletError {
+    fn from(err: CreateSurfaceError) -> Self {
+        return RugletError::CreateSurface(err);
+    }
+}
+
+impl From<RequestDeviceError> for RugletError {
+    fn from(err: RequestDeviceError) -> Self {
+        return RugletError::RequestDevice(err);
+    }
+}
+
+impl From<SurfaceError> for RugletError {
+    fn from(err: SurfaceError) -> Self {
+        return RugletError::Surface(err);
+    }
+}
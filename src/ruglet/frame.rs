@@ -1,71 +1,489 @@
+use std::ops::Range;
+
 use winit::dpi::PhysicalSize;
 
 use crate::ruglet::*;
 
-use super::texture::TextureData;
+use super::{
+    stroke::tessellate,
+    texture::{TextureData, TextureId},
+};
+
+/// Which pipeline a batch goes through, and what it draws.
+#[derive(Debug, Clone)]
+pub enum Primitive {
+    /// A range of `Frame.indices`
+    Triangles(Range<u32>),
+
+    /// A range of `Frame.quads`
+    Quads(Range<u32>),
+
+    /// Blurs what has been drawn so far, then draws it back through the quad
+    Backdrop { quad: u32, blur: f32 },
+}
+
+/// A run of draws that share a pipeline, clip area, layer and texture.
+#[derive(Debug, Clone)]
+pub struct Batch {
+    pub primitive: Primitive,
+    pub clip: Area,
+    pub layer: i32,
+
+    // None if nothing in the batch samples a texture
+    pub texture: Option<TextureId>,
+}
+
+impl Batch {
+    /// The pixels the clip area covers, as `[x0, y0, x1, y1]`.
+    /// A pixel is inside if its center is.
+    pub fn scissor(&self, size: PhysicalSize<u32>) -> [u32; 4] {
+        let x0 = self.clip.0.x.round().clamp(0., size.width as f32) as u32;
+        let y0 = self.clip.0.y.round().clamp(0., size.height as f32) as u32;
+        let x1 = self.clip.1.x.round().clamp(0., size.width as f32) as u32;
+        let y1 = self.clip.1.y.round().clamp(0., size.height as f32) as u32;
+
+        return [x0, y0, x1.max(x0), y1.max(y0)];
+    }
+}
+
+/// An outline drawn just inside the edge of a shape.
+#[derive(Debug, Clone, Copy)]
+pub struct Border {
+    pub width: f32,
+    pub color: Color,
+}
 
 pub struct Frame {
+    // The screen, in logical pixels
     pub area: Area,
+
+    // How many physical pixels there are to a logical one
+    pub scale: f32,
+
+    pub clear_color: Color,
     pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub quads: Vec<Quad>,
+    pub styles: Vec<QuadStyle>,
+    pub stops: Vec<Stop>,
+    pub batches: Vec<Batch>,
+
+    // Nested clip areas, already intersected with each other
+    clips: Vec<Area>,
+
+    // Higher layers are drawn on top of lower ones
+    layer: i32,
+
+    // Nested transforms, already combined with each other
+    transforms: Vec<Transform>,
+
+    // Textures to upload before drawing
+    pub textures: Vec<(TextureId, TextureData)>,
+
+    // Textures to let go of, that nothing will draw with again
+    pub removed_textures: Vec<TextureId>,
 
-    pub texture: TextureData,
-    pub texture_changed: bool,
+    // Whether another frame should follow this one, like while animating
+    redraw: bool,
 }
 
 impl Frame {
+    #[allow(dead_code)]
     pub fn new(size: PhysicalSize<u32>) -> Self {
+        return Frame::with_scale(size, 1.);
+    }
+
+    /// A frame for a screen of `size` physical pixels, drawn in logical
+    /// pixels that are `scale` physical pixels across.
+    pub fn with_scale(size: PhysicalSize<u32>, scale: f32) -> Self {
         return Frame {
-            // List of vertices
+            // List of vertices, and the triangles they make up
             vertices: vec![],
+            indices: vec![],
+
+            // Axis aligned rectangles, drawn one instance each
+            quads: vec![],
+
+            // What's left of the quads, shared by the ones drawn in a row
+            styles: vec![],
+
+            // The colors of every gradient, looked up by the quads
+            stops: vec![],
+
+            // The order to draw it all in
+            batches: vec![],
+            clips: vec![],
+            layer: 0,
+            transforms: vec![],
 
             // Size of the screen
             area: Area(
                 Vec2::zero(),
-                Vec2::new(size.width as f32, size.height as f32),
+                Vec2::new(size.width as f32 / scale, size.height as f32 / scale),
             ),
+            scale,
+
+            // Start from transparent black
+            clear_color: Color::TRANSPARENT,
 
-            // Default to 1x1px white texture
-            texture: TextureData::blank(),
-            texture_changed: false,
+            // Textures are kept between frames, so usually there is nothing new
+            textures: vec![],
+            removed_textures: vec![],
+
+            redraw: false,
         };
     }
 
-    pub fn quad(&mut self, area: Area, tex: Area, color: [f32; 3]) {
-        self.vertices.push(Vertex {
-            position: [area.0.x, area.0.y, 0.0],
-            tex_coords: [tex.0.x, tex.0.y],
-            color,
-        });
-        self.vertices.push(Vertex {
-            position: [area.0.x, area.1.y, 0.0],
-            tex_coords: [tex.0.x, tex.1.y],
-            color,
-        });
-        self.vertices.push(Vertex {
-            position: [area.1.x, area.1.y, 0.0],
-            tex_coords: [tex.1.x, tex.1.y],
-            color,
-        });
+    /// What the screen is filled with before anything is drawn.
+    pub fn set_clear_color(&mut self, color: Color) {
+        self.clear_color = color;
+    }
 
-        self.vertices.push(Vertex {
-            position: [area.1.x, area.1.y, 0.0],
-            tex_coords: [tex.1.x, tex.1.y],
-            color,
-        });
-        self.vertices.push(Vertex {
-            position: [area.1.x, area.0.y, 0.0],
-            tex_coords: [tex.1.x, tex.0.y],
-            color,
+    /// Only draw inside the area, until the matching `pop_clip`.
+    /// Nested clips only draw where all of them overlap. Clips stay axis
+    /// aligned on screen, so under a rotation they cover the whole
+    /// rotated area and then some.
+    pub fn push_clip(&mut self, area: Area) {
+        let area = self.transform().bounds(area);
+        let clip = self.clip().intersect(area);
+        self.clips.push(clip);
+    }
+
+    pub fn pop_clip(&mut self) {
+        self.clips.pop();
+    }
+
+    /// The area we are currently allowed to draw in, in physical pixels.
+    pub fn clip(&self) -> Area {
+        return *self.clips.last().unwrap_or(&self.screen());
+    }
+
+    /// The whole screen, in physical pixels.
+    pub fn screen(&self) -> Area {
+        return Area(Vec2::zero(), self.area.1 * self.scale);
+    }
+
+    /// Whether any of the area ends up on the screen.
+    pub fn on_screen(&self, area: Area) -> bool {
+        return self.screen().contains(self.transform().bounds(area));
+    }
+
+    /// Moves, scales or rotates everything drawn until the matching
+    /// `pop_transform`. Nested transforms apply the inner one first.
+    pub fn push_transform(&mut self, transform: Transform) {
+        let transform = self.transform().then(transform);
+        self.transforms.push(transform);
+    }
+
+    pub fn pop_transform(&mut self) {
+        self.transforms.pop();
+    }
+
+    /// Where what's drawn now ends up on screen, in physical pixels.
+    pub fn transform(&self) -> Transform {
+        let scale = Transform::scale(self.scale, self.scale);
+        return *self.transforms.last().unwrap_or(&scale);
+    }
+
+    /// Everything drawn from now on goes on this layer. Layers are drawn from
+    /// lowest to highest, and in the order they were drawn within a layer.
+    pub fn set_layer(&mut self, layer: i32) {
+        self.layer = layer;
+    }
+
+    pub fn layer(&self) -> i32 {
+        return self.layer;
+    }
+
+    /// Puts the batches in the order they should be drawn in.
+    pub fn sort_batches(&mut self) {
+        // This is a stable sort, so the order within a layer is kept
+        self.batches.sort_by_key(|batch| batch.layer);
+    }
+
+    /// Draws part of the texture, tinted by the color.
+    pub fn quad(&mut self, texture: TextureId, area: Area, tex: Area, color: Color) {
+        self.push_quad(
+            Some(texture),
+            Quad {
+                rect: [area.0.x, area.0.y, area.1.x, area.1.y],
+                uv: [tex.0.x, tex.0.y, tex.1.x, tex.1.y],
+                color: color.to_array(),
+                flags: 0,
+                ..Quad::default()
+            },
+            QuadStyle::default(),
+        );
+    }
+
+    /// Fills the area with a solid color.
+    pub fn rect(&mut self, area: Area, color: Color) {
+        self.push_quad(
+            None,
+            Quad {
+                rect: [area.0.x, area.0.y, area.1.x, area.1.y],
+                uv: [0.; 4],
+                color: color.to_array(),
+                flags: Quad::SOLID,
+                ..Quad::default()
+            },
+            QuadStyle::default(),
+        );
+    }
+
+    /// Fills the area with a solid color, rounding off the corners.
+    /// The radii go clockwise from the top left.
+    #[allow(dead_code)]
+    pub fn rounded_rect(&mut self, area: Area, radii: [f32; 4], color: Color) {
+        self.bordered_rect(area, radii, color, Border { width: 0., color });
+    }
+
+    /// Same as `rounded_rect`, with a border drawn inside the area.
+    #[allow(dead_code)]
+    pub fn bordered_rect(&mut self, area: Area, radii: [f32; 4], color: Color, border: Border) {
+        self.push_quad(
+            None,
+            Quad {
+                rect: [area.0.x, area.0.y, area.1.x, area.1.y],
+                uv: [0.; 4],
+                color: color.to_array(),
+                flags: Quad::SOLID | Quad::ROUNDED,
+                ..Quad::default()
+            },
+            QuadStyle {
+                radii,
+                border_color: border.color.to_array(),
+                border_width: border.width,
+                ..QuadStyle::default()
+            },
+        );
+    }
+
+    /// Fills the area with a gradient.
+    pub fn gradient(&mut self, area: Area, gradient: &Gradient) {
+        self.push_gradient(area, [0.; 4], Quad::SOLID, gradient);
+    }
+
+    /// Same as `gradient`, rounding off the corners like `rounded_rect`.
+    #[allow(dead_code)]
+    pub fn rounded_gradient(&mut self, area: Area, radii: [f32; 4], gradient: &Gradient) {
+        self.push_gradient(area, radii, Quad::SOLID | Quad::ROUNDED, gradient);
+    }
+
+    /// Draws the soft shadow a rounded rectangle would cast. The blur is how
+    /// far the edge spreads out, as the standard deviation in pixels.
+    #[allow(dead_code)]
+    pub fn shadow(&mut self, area: Area, radius: f32, blur: f32, offset: Vec2, color: Color) {
+        let area = Area(area.0 + offset, area.1 + offset);
+
+        self.push_quad(
+            None,
+            Quad {
+                rect: [area.0.x, area.0.y, area.1.x, area.1.y],
+                uv: [blur, 0., 0., 0.],
+                color: color.to_array(),
+                flags: Quad::SOLID | Quad::SHADOW,
+                ..Quad::default()
+            },
+            QuadStyle {
+                radii: [radius; 4],
+                ..QuadStyle::default()
+            },
+        );
+    }
+
+    /// Blurs everything underneath the area that has been drawn so far, like
+    /// frosted glass. The blur is the standard deviation in pixels.
+    /// Rotated backdrops blur what's under their bounds, not what's under them.
+    #[allow(dead_code)]
+    pub fn backdrop(&mut self, area: Area, radius: f32, blur: f32) {
+        let (w, h) = (self.screen().w(), self.screen().h());
+
+        // The blurred copy is the size of the screen
+        let screen = self.transform().bounds(area);
+        let quad = self.quads.len() as u32;
+        let style = self.push_style(QuadStyle {
+            radii: [radius; 4],
+            ..QuadStyle::default()
         });
-        self.vertices.push(Vertex {
-            position: [area.0.x, area.0.y, 0.0],
-            tex_coords: [tex.0.x, tex.0.y],
-            color,
+        self.quads.push(Quad {
+            rect: [area.0.x, area.0.y, area.1.x, area.1.y],
+            uv: [
+                screen.0.x / w,
+                screen.0.y / h,
+                screen.1.x / w,
+                screen.1.y / h,
+            ],
+            color: Color::WHITE.to_array(),
+            flags: Quad::ROUNDED | Quad::PREMULTIPLIED,
+            style,
         });
+
+        // Blurred on screen, so in physical pixels
+        let blur = blur * self.transform().scale_factor();
+        self.batch(Primitive::Backdrop { quad, blur }, None);
+    }
+
+    /// Draws a straight line between two points.
+    #[allow(dead_code)]
+    pub fn line(&mut self, from: Vec2, to: Vec2, stroke: Stroke) {
+        self.polyline(&[from, to], stroke);
+    }
+
+    /// Draws a line through all the points.
+    #[allow(dead_code)]
+    pub fn polyline(&mut self, points: &[Vec2], stroke: Stroke) {
+        let (vertices, indices) = tessellate(points, &stroke, self.transform().scale_factor());
+
+        if !indices.is_empty() {
+            self.triangles(None, &vertices, &indices);
+        }
     }
 
-    pub fn set_texture(&mut self, texture: TextureData) {
-        self.texture = texture;
-        self.texture_changed = true;
+    /// Draws arbitrary geometry. The indices are relative to `vertices`.
+    /// Without a texture, every vertex has to be a solid fill.
+    #[allow(dead_code)]
+    pub fn triangles(&mut self, texture: Option<TextureId>, vertices: &[Vertex], indices: &[u32]) {
+        let base = self.vertices.len() as u32;
+        let start = self.indices.len() as u32;
+
+        // Vertices are transformed here, so the shader doesn't have to
+        let transform = self.transform();
+        self.vertices.extend(vertices.iter().map(|vertex| {
+            let [x, y, z] = vertex.position;
+            let p = transform.apply(Vec2::new(x, y));
+            Vertex {
+                position: [p.x, p.y, z],
+                ..*vertex
+            }
+        }));
+        self.indices.extend(indices.iter().map(|i| base + i));
+
+        self.batch(
+            Primitive::Triangles(start..self.indices.len() as u32),
+            texture,
+        );
+    }
+
+    /// Draws another frame right after this one, instead of waiting for
+    /// something to happen. Used to keep animations going.
+    pub fn request_redraw(&mut self) {
+        self.redraw = true;
+    }
+
+    pub fn redraw_requested(&self) -> bool {
+        return self.redraw;
+    }
+
+    /// How many bytes of geometry this frame sends to the GPU.
+    pub fn upload_size(&self) -> usize {
+        return std::mem::size_of_val(self.vertices.as_slice())
+            + std::mem::size_of_val(self.indices.as_slice())
+            + std::mem::size_of_val(self.quads.as_slice())
+            + std::mem::size_of_val(self.styles.as_slice())
+            + std::mem::size_of_val(self.stops.as_slice());
+    }
+
+    /// Uploads the texture, replacing the one with the same id if there is
+    /// one. Textures are kept around, so they only need to be set once.
+    pub fn set_texture(&mut self, id: TextureId, texture: TextureData) {
+        self.textures.retain(|(other, _)| *other != id);
+        self.removed_textures.retain(|other| *other != id);
+        self.textures.push((id, texture));
+    }
+
+    /// Frees the texture, and the copy kept to upload it again after the
+    /// device is lost. Nothing should be drawn with it afterwards.
+    pub fn remove_texture(&mut self, id: TextureId) {
+        self.textures.retain(|(other, _)| *other != id);
+        self.removed_textures.push(id);
+    }
+
+    fn push_gradient(&mut self, area: Area, radii: [f32; 4], flags: u32, gradient: &Gradient) {
+        // Without any colors there is nothing to draw
+        if gradient.stops.is_empty() {
+            return;
+        }
+
+        let first = self.stops.len() as u32;
+        self.stops
+            .extend(gradient.stops.iter().map(|(offset, color)| Stop {
+                color: color.to_array(),
+                offset: *offset,
+                ..Stop::default()
+            }));
+
+        let radial = match gradient.kind {
+            GradientKind::Linear { .. } => 0,
+            GradientKind::Radial { .. } => Quad::RADIAL,
+        };
+
+        self.push_quad(
+            None,
+            Quad {
+                rect: [area.0.x, area.0.y, area.1.x, area.1.y],
+                uv: gradient.geometry(area),
+                flags: flags | Quad::GRADIENT | radial,
+                ..Quad::default()
+            },
+            QuadStyle {
+                radii,
+                stops: [first, gradient.stops.len() as u32],
+                ..QuadStyle::default()
+            },
+        );
+    }
+
+    fn push_quad(&mut self, texture: Option<TextureId>, quad: Quad, style: QuadStyle) {
+        let style = self.push_style(style);
+        let i = self.quads.len() as u32;
+        self.quads.push(Quad { style, ..quad });
+
+        self.batch(Primitive::Quads(i..i + 1), texture);
+    }
+
+    // Moves the style under the current transform, and returns its index.
+    // Quads drawn in a row, like the glyphs of a line, usually share one
+    fn push_style(&mut self, style: QuadStyle) -> u32 {
+        let style = QuadStyle {
+            transform: self.transform().to_array(),
+            ..style
+        };
+
+        if self.styles.last() != Some(&style) {
+            self.styles.push(style);
+        }
+
+        return self.styles.len() as u32 - 1;
+    }
+
+    fn batch(&mut self, primitive: Primitive, texture: Option<TextureId>) {
+        let clip = self.clip();
+        let layer = self.layer;
+
+        // Merge with the previous batch if we can
+        if let Some(last) = self.batches.last_mut() {
+            let same_texture =
+                last.texture.is_none() || texture.is_none() || last.texture == texture;
+
+            if last.clip == clip && last.layer == layer && same_texture {
+                match (&mut last.primitive, &primitive) {
+                    (Primitive::Triangles(range), Primitive::Triangles(next))
+                    | (Primitive::Quads(range), Primitive::Quads(next)) => {
+                        range.end = next.end;
+                        last.texture = last.texture.or(texture);
+                        return;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        self.batches.push(Batch {
+            primitive,
+            clip,
+            layer,
+            texture,
+        });
     }
 }
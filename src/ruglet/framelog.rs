@@ -0,0 +1,97 @@
+use std::{env, time::Duration};
+
+use winit::dpi::PhysicalSize;
+
+/// Prints a line for every frame the window presents, turned on by setting
+/// `RUGLET_FRAME_LOG`. While resizing, every size the window goes through
+/// should get a frame of its own, at that size.
+pub struct FrameLog {
+    enabled: bool,
+    frames: u32,
+
+    // Sizes the window was resized to, but that never got drawn
+    dropped: u32,
+
+    // Frames drawn at a different size than the window had when they were shown
+    stretched: u32,
+
+    // Frames the surface wasn't ready for
+    skipped: u32,
+
+    // The last size we were resized to, until it's drawn
+    pending: Option<PhysicalSize<u32>>,
+}
+
+impl FrameLog {
+    pub fn from_env() -> FrameLog {
+        return FrameLog {
+            enabled: env::var_os("RUGLET_FRAME_LOG").is_some(),
+            frames: 0,
+            dropped: 0,
+            stretched: 0,
+            skipped: 0,
+            pending: None,
+        };
+    }
+
+    pub fn resized(&mut self, size: PhysicalSize<u32>) {
+        if !self.enabled {
+            return;
+        }
+
+        // Resized again before the last size was drawn
+        if let Some(skipped) = self.pending.replace(size) {
+            self.dropped += 1;
+            println!("[frame] dropped {}x{}", skipped.width, skipped.height);
+        }
+    }
+
+    /// After a frame of `size` was thrown away, because the surface was lost,
+    /// outdated or timed out. The size stays pending until it's drawn.
+    pub fn skipped(&mut self, cause: &str, size: PhysicalSize<u32>) {
+        if !self.enabled {
+            return;
+        }
+
+        self.skipped += 1;
+        println!(
+            "[frame] skipped {} {}x{} (skipped {})",
+            cause, size.width, size.height, self.skipped
+        );
+    }
+
+    /// After a frame drawn at `size` has been shown in a window of `window`.
+    pub fn presented(
+        &mut self,
+        cause: &str,
+        size: PhysicalSize<u32>,
+        window: PhysicalSize<u32>,
+        took: Duration,
+    ) {
+        if !self.enabled {
+            return;
+        }
+
+        self.frames += 1;
+        if self.pending == Some(size) {
+            self.pending = None;
+        }
+
+        let stretched = size != window;
+        if stretched {
+            self.stretched += 1;
+        }
+
+        println!(
+            "[frame {}] {} {}x{} in {:.2?}{} (dropped {}, stretched {})",
+            self.frames,
+            cause,
+            size.width,
+            size.height,
+            took,
+            if stretched { " STRETCHED" } else { "" },
+            self.dropped,
+            self.stretched
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use super::{Area, Color, Vec2};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GradientKind {
+    /// Across the whole area, in the direction of the angle. An angle of 0
+    /// goes from left to right, and larger angles turn it clockwise.
+    Linear { angle: f32 },
+
+    /// Out from the center, which is a fraction of the size of the area,
+    /// reaching the last stop at the radius, in pixels.
+    Radial { center: Vec2, radius: f32 },
+}
+
+/// Colors that blend into each other, evaluated per pixel in the shader.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    pub kind: GradientKind,
+
+    // Offsets go from 0 to 1, in order
+    pub stops: Vec<(f32, Color)>,
+}
+
+impl Gradient {
+    pub fn linear(angle: f32, stops: &[(f32, Color)]) -> Gradient {
+        return Gradient {
+            kind: GradientKind::Linear { angle },
+            stops: stops.to_vec(),
+        };
+    }
+
+    #[allow(dead_code)]
+    pub fn radial(center: Vec2, radius: f32, stops: &[(f32, Color)]) -> Gradient {
+        return Gradient {
+            kind: GradientKind::Radial { center, radius },
+            stops: stops.to_vec(),
+        };
+    }
+
+    /// Where the gradient lies in the area, relative to its center, packed
+    /// the way `Quad.uv` expects it.
+    pub fn geometry(&self, area: Area) -> [f32; 4] {
+        match self.kind {
+            GradientKind::Linear { angle } => {
+                // Long enough that the corners get the first and last stops
+                let dir = Vec2::new(1., 0.).rotate(angle);
+                let half = (area.w() * dir.x.abs() + area.h() * dir.y.abs()) / 2.;
+                let end = dir * half;
+
+                return [-end.x, -end.y, end.x, end.y];
+            }
+            GradientKind::Radial { center, radius } => {
+                let x = (center.x - 0.5) * area.w();
+                let y = (center.y - 0.5) * area.h();
+
+                return [x, y, radius, 0.];
+            }
+        }
+    }
+}
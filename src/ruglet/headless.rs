@@ -0,0 +1,132 @@
+use super::{painter::Painter, texture::TextureData, Frame, RugletError};
+use wgpu::*;
+use winit::dpi::PhysicalSize;
+
+// Matches the sRGB surface format the window renderer prefers
+const FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+/// Draws frames into an offscreen texture instead of a window.
+pub struct Headless {
+    pub size: PhysicalSize<u32>,
+    pub painter: Painter,
+
+    // What we draw into
+    target: wgpu::Texture,
+}
+
+impl Headless {
+    pub async fn new(size: PhysicalSize<u32>) -> Result<Headless, RugletError> {
+        let instance = Instance::new(&InstanceDescriptor::default());
+
+        // Prefer the software adapter, so we work on machines without a GPU
+        let adapter = match request_adapter(&instance, true).await {
+            Some(adapter) => adapter,
+            None => request_adapter(&instance, false)
+                .await
+                .ok_or(RugletError::NoAdapter)?,
+        };
+
+        // Single sampled, so it draws the same pixels as `Software`
+        let mut painter = Painter::new(&adapter, FORMAT, 1).await?;
+        painter.resize(size);
+
+        let target = create_target(&painter.device, size);
+
+        return Ok(Headless {
+            size,
+            painter,
+            target,
+        });
+    }
+
+    /// Draws the frame and reads it back as tightly packed RGBA bytes.
+    /// Starts over with a new device if the old one was lost.
+    pub fn render(&mut self, frame: Frame) -> Result<TextureData, RugletError> {
+        if self.painter.lost() {
+            self.painter.recover()?;
+            self.target = create_target(&self.painter.device, self.size);
+        }
+
+        let view = self.target.create_view(&TextureViewDescriptor::default());
+        self.painter.paint(frame, &view);
+
+        return Ok(self.read());
+    }
+
+    fn read(&self) -> TextureData {
+        let device = &self.painter.device;
+        let (width, height) = (self.size.width, self.size.height);
+
+        // Rows of a buffer copy have to be aligned
+        let row = 4 * width;
+        let padded_row = row.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("headless_readback"),
+            size: (padded_row * height) as BufferAddress,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        // Copy the target into something we can map
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+        encoder.copy_texture_to_buffer(
+            TexelCopyTextureInfo {
+                texture: &self.target,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            self.target.size(),
+        );
+        self.painter.queue.submit(std::iter::once(encoder.finish()));
+
+        // Wait for the GPU to finish
+        let slice = buffer.slice(..);
+        slice.map_async(MapMode::Read, |result| result.unwrap());
+        device.poll(Maintain::Wait);
+
+        // Strip the row padding
+        let mut bytes = Vec::with_capacity((row * height) as usize);
+        for chunk in slice.get_mapped_range().chunks(padded_row as usize) {
+            bytes.extend_from_slice(&chunk[..row as usize]);
+        }
+
+        return TextureData::new(bytes, (width, height));
+    }
+}
+
+async fn request_adapter(instance: &Instance, fallback: bool) -> Option<Adapter> {
+    return instance
+        .request_adapter(&RequestAdapterOptions {
+            power_preference: PowerPreference::LowPower,
+            compatible_surface: None,
+            force_fallback_adapter: fallback,
+        })
+        .await;
+}
+
+fn create_target(device: &Device, size: PhysicalSize<u32>) -> wgpu::Texture {
+    return device.create_texture(&TextureDescriptor {
+        label: Some("headless_target"),
+        size: Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: FORMAT,
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+}
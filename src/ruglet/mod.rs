@@ -1,14 +1,32 @@
 mod application;
 mod area;
 mod bindings;
+mod blur;
+mod buffer;
+mod color;
+mod error;
 mod frame;
+mod framelog;
+mod gradient;
+mod headless;
+mod painter;
 mod renderer;
+mod software;
+mod stroke;
 mod texture;
+mod transform;
 mod vertex;
 
 // Publish all
 pub use application::*;
 pub use area::*;
+pub use color::*;
+pub use error::*;
 pub use frame::*;
+pub use gradient::*;
+pub use headless::*;
+pub use software::*;
+pub use stroke::*;
 pub use texture::*;
+pub use transform::*;
 pub use vertex::*;
@@ -0,0 +1,452 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use super::{
+    bindings::*,
+    blur::Blur,
+    buffer::GrowBuffer,
+    texture::{create_texture, TextureData, TextureId},
+    vertex::{Quad, Vertex},
+    Batch, Frame, Primitive,
+};
+use wgpu::*;
+use winit::dpi::PhysicalSize;
+
+/// Everything needed to draw a frame, independent of where it ends up.
+pub struct Painter {
+    pub size: PhysicalSize<u32>,
+    pub format: TextureFormat,
+
+    // Above 1, everything is drawn into `msaa` and resolved from there
+    pub sample_count: u32,
+    pub msaa: Option<TextureView>,
+
+    // core wgpu
+    pub adapter: Adapter,
+    pub device: Device,
+    pub queue: Queue,
+    pub render_pipeline: RenderPipeline,
+    pub quad_pipeline: RenderPipeline,
+
+    // wgpu utils
+    pub bindings: Bindings,
+    pub textures: HashMap<TextureId, BindGroup>,
+
+    // The last data set for each texture, to upload again if the device is
+    // lost. Setting a texture replaces its copy, and removing it drops it
+    uploads: HashMap<TextureId, TextureData>,
+
+    // Set by wgpu once the device is gone
+    lost: Arc<AtomicBool>,
+    pub screen_size: Buffer,
+    pub vertex_buffer: GrowBuffer,
+    pub index_buffer: GrowBuffer,
+    pub quad_buffer: GrowBuffer,
+    pub style_buffer: GrowBuffer,
+    pub stop_buffer: GrowBuffer,
+    pub blur: Blur,
+}
+
+impl Painter {
+    /// The sample count has to be one the adapter supports for the format.
+    pub async fn new(
+        adapter: &Adapter,
+        format: TextureFormat,
+        sample_count: u32,
+    ) -> Result<Painter, RequestDeviceError> {
+        // These are used to communicate with the GPU
+        let (device, queue) = adapter
+            .request_device(&DeviceDescriptor::default(), None)
+            .await?;
+
+        // Only reported while polling, see `lost`
+        let lost = Arc::new(AtomicBool::new(false));
+        let flag = lost.clone();
+        device.set_device_lost_callback(move |_, _| flag.store(true, Ordering::Release));
+
+        // Reused every frame, so we don't have to keep reallocating it
+        let vertex_buffer = GrowBuffer::new(&device, "Vertex Buffer", BufferUsages::VERTEX);
+        let index_buffer = GrowBuffer::new(&device, "Index Buffer", BufferUsages::INDEX);
+        let quad_buffer = GrowBuffer::new(&device, "Quad Buffer", BufferUsages::VERTEX);
+        let style_buffer = GrowBuffer::new(&device, "Style Buffer", BufferUsages::STORAGE);
+        let stop_buffer = GrowBuffer::new(&device, "Stop Buffer", BufferUsages::STORAGE);
+
+        // Get the bindgroups for the shader
+        let screen_size = create_screen_size_buffer(&device);
+        let bindings = create_bindings(
+            &device,
+            &queue,
+            &screen_size,
+            &stop_buffer.buffer,
+            &style_buffer.buffer,
+        );
+
+        // Create the shader + it's render pipeline
+        // If you edit the shader, you need to update this function
+        let render_pipeline = create_render_pipeline(
+            &device,
+            format,
+            sample_count,
+            &bindings,
+            "vs_main",
+            Vertex::desc(),
+        );
+        let quad_pipeline = create_render_pipeline(
+            &device,
+            format,
+            sample_count,
+            &bindings,
+            "vs_quad",
+            Quad::desc(),
+        );
+        let blur = Blur::new(&device, format);
+
+        let size = PhysicalSize::new(1, 1);
+        let msaa = create_msaa_target(&device, format, size, sample_count);
+
+        return Ok(Painter {
+            size,
+            format,
+            sample_count,
+            msaa,
+            adapter: adapter.clone(),
+            device,
+            queue,
+            render_pipeline,
+            quad_pipeline,
+            bindings,
+            textures: HashMap::new(),
+            uploads: HashMap::new(),
+            lost,
+            screen_size,
+            vertex_buffer,
+            index_buffer,
+            quad_buffer,
+            style_buffer,
+            stop_buffer,
+            blur,
+        });
+    }
+
+    pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
+        self.size = new_size;
+        self.blur.resize(new_size);
+        self.msaa = create_msaa_target(&self.device, self.format, new_size, self.sample_count);
+
+        // Update the uniform with the screen size
+        update_screen_size(&self.queue, &self.screen_size, new_size);
+    }
+
+    /// Whether the device has been lost, after which nothing drawn with it
+    /// shows up until `recover` is called.
+    pub fn lost(&self) -> bool {
+        self.device.poll(Maintain::Poll);
+        return self.lost.load(Ordering::Acquire);
+    }
+
+    /// Starts over with a new device, since everything made with the old one
+    /// is gone too. The textures uploaded so far are uploaded again.
+    pub fn recover(&mut self) -> Result<(), RequestDeviceError> {
+        let mut painter =
+            pollster::block_on(Painter::new(&self.adapter, self.format, self.sample_count))?;
+        painter.resize(self.size);
+
+        for (id, data) in self.uploads.drain() {
+            painter.upload(id, data);
+        }
+
+        *self = painter;
+        return Ok(());
+    }
+
+    /// Lets go of the frame's removed textures and uploads its new ones,
+    /// taking them out of the frame. Done even for frames that are skipped,
+    /// since whoever sent the textures won't send them again.
+    pub fn update_textures(&mut self, frame: &mut Frame) {
+        for id in frame.removed_textures.drain(..) {
+            self.textures.remove(&id);
+            self.uploads.remove(&id);
+        }
+        for (id, data) in frame.textures.drain(..) {
+            self.upload(id, data);
+        }
+    }
+
+    fn upload(&mut self, id: TextureId, data: TextureData) {
+        let texture = create_texture(&self.device, &self.queue, &data);
+        let group = create_texture_group(&self.device, &self.bindings, &texture);
+        self.textures.insert(id, group);
+        self.uploads.insert(id, data);
+    }
+
+    pub fn paint(&mut self, mut frame: Frame, view: &TextureView) {
+        frame.sort_batches();
+
+        // Upload the vertices
+        self.vertex_buffer.write(
+            &self.device,
+            &self.queue,
+            bytemuck::cast_slice(frame.vertices.as_slice()),
+        );
+        self.index_buffer.write(
+            &self.device,
+            &self.queue,
+            bytemuck::cast_slice(frame.indices.as_slice()),
+        );
+        self.quad_buffer.write(
+            &self.device,
+            &self.queue,
+            bytemuck::cast_slice(frame.quads.as_slice()),
+        );
+        let styles_grown = self.style_buffer.write(
+            &self.device,
+            &self.queue,
+            bytemuck::cast_slice(frame.styles.as_slice()),
+        );
+        let stops_grown = self.stop_buffer.write(
+            &self.device,
+            &self.queue,
+            bytemuck::cast_slice(frame.stops.as_slice()),
+        );
+        if styles_grown || stops_grown {
+            update_storage_bindgroup(
+                &self.device,
+                &mut self.bindings,
+                &self.stop_buffer.buffer,
+                &self.style_buffer.buffer,
+            );
+        }
+
+        self.update_textures(&mut frame);
+
+        // Backdrops need to read back what's been drawn, so those frames
+        // are drawn offscreen and copied over at the end
+        let backdrops = frame
+            .batches
+            .iter()
+            .any(|batch| matches!(batch.primitive, Primitive::Backdrop { .. }));
+        if backdrops {
+            self.blur.prepare(&self.device);
+        }
+        let target = if backdrops { self.blur.scene() } else { view };
+
+        // Create the actual commands to send to the GPU
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor::default());
+
+        // Cleared to the premultiplied color, like everything drawn after it
+        let [r, g, b, a] = frame.clear_color.premultiplied();
+        let mut load = LoadOp::Clear(Color {
+            r: r as f64,
+            g: g as f64,
+            b: b as f64,
+            a: a as f64,
+        });
+
+        // Each backdrop starts a new render pass, after blurring the ones before it
+        let mut batches = frame.batches.into_iter().peekable();
+        let mut backdrop = None;
+        loop {
+            {
+                let mut render_pass =
+                    create_render_pass(target, self.msaa.as_ref(), &mut encoder, load);
+                load = LoadOp::Load;
+
+                // Set the bind groups
+                render_pass.set_bind_group(0, &self.bindings[0].group, &[]);
+                render_pass.set_bind_group(
+                    STORAGE_BIND_GROUP as u32,
+                    &self.bindings[STORAGE_BIND_GROUP].group,
+                    &[],
+                );
+
+                if let Some((batch, group)) = backdrop.take() {
+                    self.draw(&mut render_pass, &batch, &group);
+                }
+
+                // Draw everything in order, switching shaders as we go
+                while let Some(batch) =
+                    batches.next_if(|batch| !matches!(batch.primitive, Primitive::Backdrop { .. }))
+                {
+                    // Solid fills don't look at the texture, so they get the blank one
+                    let texture = batch
+                        .texture
+                        .and_then(|id| self.textures.get(&id))
+                        .unwrap_or(&self.bindings[TEXTURE_BIND_GROUP].group);
+                    self.draw(&mut render_pass, &batch, texture);
+                }
+            }
+
+            let Some(batch) = batches.next() else {
+                break;
+            };
+            let Primitive::Backdrop { quad, blur } = batch.primitive else {
+                unreachable!();
+            };
+
+            // Blur what's underneath, then draw it back through the quad
+            let rect = frame.quads[quad as usize].bounds(&frame.styles);
+            let blurred = self.blur.blur(&self.device, &mut encoder, rect, blur);
+            let group = create_texture_group(&self.device, &self.bindings, blurred);
+            backdrop = Some((batch, group));
+        }
+
+        if backdrops {
+            self.blur.copy(&self.device, &mut encoder, view);
+        }
+
+        // Send the commands to the GPU
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    fn draw(&self, render_pass: &mut RenderPass, batch: &Batch, texture: &BindGroup) {
+        // Skip anything that has been clipped away completely
+        let [x0, y0, x1, y1] = batch.scissor(self.size);
+        if x0 == x1 || y0 == y1 {
+            return;
+        }
+        render_pass.set_scissor_rect(x0, y0, x1 - x0, y1 - y0);
+        render_pass.set_bind_group(TEXTURE_BIND_GROUP as u32, texture, &[]);
+
+        match batch.primitive {
+            Primitive::Triangles(ref indices) => {
+                render_pass.set_pipeline(&self.render_pipeline);
+                render_pass.set_vertex_buffer(0, self.vertex_buffer.slice());
+                render_pass.set_index_buffer(self.index_buffer.slice(), IndexFormat::Uint32);
+                render_pass.draw_indexed(indices.clone(), 0, 0..1);
+            }
+            Primitive::Quads(ref quads) => {
+                render_pass.set_pipeline(&self.quad_pipeline);
+                render_pass.set_vertex_buffer(0, self.quad_buffer.slice());
+                render_pass.draw(0..6, quads.clone());
+            }
+            Primitive::Backdrop { quad, .. } => {
+                render_pass.set_pipeline(&self.quad_pipeline);
+                render_pass.set_vertex_buffer(0, self.quad_buffer.slice());
+                render_pass.draw(0..6, quad..quad + 1);
+            }
+        }
+    }
+}
+
+fn create_render_pass<'a>(
+    view: &'a TextureView,
+    msaa: Option<&'a TextureView>,
+    encoder: &'a mut CommandEncoder,
+    load: LoadOp<Color>,
+) -> RenderPass<'a> {
+    // Multisampled passes resolve into the view as they end. The samples are
+    // kept, since a backdrop can split the frame over more than one pass
+    let (view, resolve_target) = match msaa {
+        Some(msaa) => (msaa, Some(view)),
+        None => (view, None),
+    };
+
+    return encoder.begin_render_pass(&RenderPassDescriptor {
+        label: Some("Render Pass"),
+        color_attachments: &[Some(RenderPassColorAttachment {
+            view,
+            resolve_target,
+            ops: Operations {
+                load,
+                store: StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    });
+}
+
+fn create_msaa_target(
+    device: &Device,
+    format: TextureFormat,
+    size: PhysicalSize<u32>,
+    sample_count: u32,
+) -> Option<TextureView> {
+    if sample_count <= 1 {
+        return None;
+    }
+
+    let texture = device.create_texture(&TextureDescriptor {
+        label: Some("msaa_target"),
+        size: Extent3d {
+            width: size.width,
+            height: size.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: TextureDimension::D2,
+        format,
+        usage: TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+
+    return Some(texture.create_view(&TextureViewDescriptor::default()));
+}
+
+fn create_render_pipeline(
+    device: &Device,
+    format: TextureFormat,
+    sample_count: u32,
+    bindings: &Bindings,
+    entry_point: &str,
+    buffer: VertexBufferLayout,
+) -> RenderPipeline {
+    // Load the shader
+    let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+    // What needs to be passed in to the shader?
+    let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some("Render Pipeline Layout"),
+        bind_group_layouts: &bindings.iter().map(|b| &b.layout).collect::<Vec<_>>(),
+        push_constant_ranges: &[],
+    });
+
+    // Create the render pipeline for the shader
+    return device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: Some(&render_pipeline_layout),
+        vertex: VertexState {
+            module: &shader,
+            entry_point: Some(entry_point),
+            buffers: &[buffer],
+            compilation_options: PipelineCompilationOptions::default(),
+        },
+        fragment: Some(FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(ColorTargetState {
+                format,
+                blend: Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                write_mask: ColorWrites::ALL,
+            })],
+            compilation_options: PipelineCompilationOptions::default(),
+        }),
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: FrontFace::Ccw,
+            // Tessellated lines don't keep to a winding order
+            cull_mode: None,
+            polygon_mode: PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    });
+}
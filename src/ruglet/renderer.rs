@@ -1,5 +1,4 @@
-use super::{bindings::*, texture::create_texture, vertex::Vertex, Frame};
-use wgpu::util::DeviceExt;
+use super::{painter::Painter, Frame, RugletError};
 use wgpu::*;
 use winit::{dpi::PhysicalSize, window::Window};
 
@@ -10,23 +9,20 @@ pub struct Renderer<'a> {
 
     // core wgpu
     pub surface: Surface<'a>,
-    pub device: Device,
-    pub queue: Queue,
     pub config: SurfaceConfiguration,
-    pub render_pipeline: RenderPipeline,
-
-    // wgpu utils
-    pub bindings: Bindings,
+    pub painter: Painter,
 }
 
 impl<'a> Renderer<'a> {
-    pub async fn new(window: &'a Window) -> Renderer<'a> {
+    /// With a sample count above 1, lines and rotated shapes get smoothed
+    /// out with MSAA. Falls back to 1 if the GPU can't do that many.
+    pub async fn new(window: &'a Window, sample_count: u32) -> Result<Renderer<'a>, RugletError> {
         // Get the size of the window
         let size = window.inner_size();
 
         // Attach our seleves to the current window
         let instance = Instance::new(&InstanceDescriptor::default());
-        let surface = instance.create_surface(window).unwrap();
+        let surface = instance.create_surface(window)?;
 
         // What GPU do we have?
         let adapter = instance
@@ -36,39 +32,31 @@ impl<'a> Renderer<'a> {
                 force_fallback_adapter: false,
             })
             .await
-            .unwrap();
-
-        // These are used to communicate with the GPU
-        let (device, queue) = adapter
-            .request_device(&DeviceDescriptor::default(), None)
-            .await
-            .unwrap();
+            .ok_or(RugletError::NoAdapter)?;
 
         // Configure the surface
         let config = create_surface_config(&surface, &adapter, size);
 
-        // Get the bindgroups for the shader
-        let bindings = create_bindings(&device, &queue);
+        // Not every format can be multisampled, or by every count
+        let features = adapter.get_texture_format_features(config.format);
+        let sample_count = match features.flags.sample_count_supported(sample_count) {
+            true => sample_count,
+            false => 1,
+        };
 
-        // Create the shader + it's render pipeline
-        // If you edit the shader, you need to update this function
-        let render_pipeline = create_render_pipeline(&device, &config, &bindings);
+        // Set up everything we need to draw
+        let painter = Painter::new(&adapter, config.format, sample_count).await?;
 
-        return Self {
+        return Ok(Self {
             // winit trackers
             window,
             size,
 
             // core wgpu
             surface,
-            device,
-            queue,
             config,
-            render_pipeline,
-
-            // wgpu utils
-            bindings,
-        };
+            painter,
+        });
     }
 
     pub fn window(&self) -> &Window {
@@ -81,88 +69,59 @@ impl<'a> Renderer<'a> {
             self.size = new_size;
             self.config.width = new_size.width;
             self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
+            self.surface.configure(&self.painter.device, &self.config);
 
-            // Update the bindgroup for the texture
-            update_screen_size_bindgroup(&self.device, &mut self.bindings, new_size);
+            // Let the shader know about the new size
+            self.painter.resize(new_size);
         }
     }
 
-    pub fn render(&mut self, frame: Frame) -> Result<(), SurfaceError> {
-        // Make sure we actually have something to render
-        if frame.vertices.len() == 0 {
-            return Ok(());
+    /// Returns false if the frame was skipped, because the surface wasn't
+    /// ready for it. Another redraw is requested when that happens. The
+    /// textures it set or removed are still taken care of.
+    pub fn render(&mut self, mut frame: Frame) -> Result<bool, RugletError> {
+        // Nothing made with a lost device works, the surface config included
+        if self.painter.lost() {
+            self.painter.recover()?;
+            self.surface.configure(&self.painter.device, &self.config);
         }
 
+        // A skipped frame only skips drawing, its textures still go through
+        self.painter.update_textures(&mut frame);
+
         // Get the current texture to render to
-        let output = self.surface.get_current_texture()?;
-
-        // Create the actual commands to send to the GPU
-        let mut encoder = self
-            .device
-            .create_command_encoder(&CommandEncoderDescriptor::default());
-
-        {
-            // What are we doing this frame?
-            let mut render_pass = create_render_pass(&output, &mut encoder);
-
-            // Add the shader
-            render_pass.set_pipeline(&self.render_pipeline);
-
-            if frame.texture_changed {
-                update_texture_bindgroup(
-                    &self.device,
-                    &mut self.bindings,
-                    create_texture(&self.device, &self.queue, frame.texture),
-                );
+        let mut reconfigured = false;
+        let output = loop {
+            match self.surface.get_current_texture() {
+                Ok(output) => break output,
+
+                // The window changed under us, so set the surface up again
+                Err(SurfaceError::Lost | SurfaceError::Outdated) if !reconfigured => {
+                    self.surface.configure(&self.painter.device, &self.config);
+                    reconfigured = true;
+                }
+
+                // Still not ready, or the GPU is busy, so try again next frame
+                Err(SurfaceError::Lost | SurfaceError::Outdated | SurfaceError::Timeout) => {
+                    self.window.request_redraw();
+                    return Ok(false);
+                }
+
+                Err(err) => return Err(err.into()),
             }
+        };
+        let view = output
+            .texture
+            .create_view(&TextureViewDescriptor::default());
 
-            // Set the bind groups
-            render_pass.set_bind_group(0, &self.bindings[0].group, &[]);
-            render_pass.set_bind_group(1, &self.bindings[1].group, &[]);
-
-            // Draw the vertices
-            let vertex_buffer = self
-                .device
-                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Vertex Buffer"),
-                    contents: bytemuck::cast_slice(&frame.vertices.as_slice()),
-                    usage: BufferUsages::VERTEX,
-                });
-
-            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-            render_pass.draw(0..(frame.vertices.len() as u32), 0..1);
-        }
-
-        // Send the commands to the GPU and show the output
-        self.queue.submit(std::iter::once(encoder.finish()));
+        // Draw the frame and show the output
+        self.painter.paint(frame, &view);
         output.present();
 
-        return Ok(());
+        return Ok(true);
     }
 }
 
-fn create_render_pass<'a>(
-    output: &'a SurfaceTexture,
-    encoder: &'a mut CommandEncoder,
-) -> RenderPass<'a> {
-    let view = output
-        .texture
-        .create_view(&TextureViewDescriptor::default());
-
-    return encoder.begin_render_pass(&RenderPassDescriptor {
-        label: Some("Render Pass"),
-        color_attachments: &[Some(RenderPassColorAttachment {
-            view: &view,
-            resolve_target: None,
-            ops: Operations::default(),
-        })],
-        depth_stencil_attachment: None,
-        occlusion_query_set: None,
-        timestamp_writes: None,
-    });
-}
-
 fn create_surface_config(
     surface: &Surface,
     adapter: &Adapter,
@@ -177,69 +136,23 @@ fn create_surface_config(
         .copied()
         .unwrap_or(surface_caps.formats[0]);
 
+    // Frames are drawn during resizes, so they need to be shown in order and
+    // as soon as possible. Fifo is always there, and keeps the window system
+    // from showing an older size once a newer one has been drawn
+    let mut present_mode = surface_caps.present_modes[0];
+    if surface_caps.present_modes.contains(&PresentMode::Fifo) {
+        present_mode = PresentMode::Fifo;
+    }
+
     return SurfaceConfiguration {
         usage: TextureUsages::RENDER_ATTACHMENT,
         format: surface_format,
         width: size.width,
         height: size.height,
-        present_mode: surface_caps.present_modes[0],
+        present_mode,
         alpha_mode: surface_caps.alpha_modes[0],
         view_formats: vec![],
-        desired_maximum_frame_latency: 2,
+        // Don't queue up frames of a size the window no longer has
+        desired_maximum_frame_latency: 1,
     };
 }
-
-fn create_render_pipeline(
-    device: &Device,
-    config: &SurfaceConfiguration,
-    bindings: &Bindings,
-) -> RenderPipeline {
-    // Load the shader
-    let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
-
-    // What needs to be passed in to the shader?
-    let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-        label: Some("Render Pipeline Layout"),
-        bind_group_layouts: &bindings.iter().map(|b| &b.layout).collect::<Vec<_>>(),
-        push_constant_ranges: &[],
-    });
-
-    // Create the render pipeline for the shader
-    return device.create_render_pipeline(&RenderPipelineDescriptor {
-        label: Some("Render Pipeline"),
-        layout: Some(&render_pipeline_layout),
-        vertex: VertexState {
-            module: &shader,
-            entry_point: Some("vs_main"),
-            buffers: &[Vertex::desc()],
-            compilation_options: PipelineCompilationOptions::default(),
-        },
-        fragment: Some(FragmentState {
-            module: &shader,
-            entry_point: Some("fs_main"),
-            targets: &[Some(ColorTargetState {
-                format: config.format,
-                blend: Some(BlendState::ALPHA_BLENDING),
-                write_mask: ColorWrites::ALL,
-            })],
-            compilation_options: PipelineCompilationOptions::default(),
-        }),
-        primitive: PrimitiveState {
-            topology: PrimitiveTopology::TriangleList,
-            strip_index_format: None,
-            front_face: FrontFace::Ccw,
-            cull_mode: Some(Face::Back),
-            polygon_mode: PolygonMode::Fill,
-            unclipped_depth: false,
-            conservative: false,
-        },
-        depth_stencil: None,
-        multisample: MultisampleState {
-            count: 1,
-            mask: !0,
-            alpha_to_coverage_enabled: false,
-        },
-        multiview: None,
-        cache: None,
-    });
-}
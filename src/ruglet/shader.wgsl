@@ -1,13 +1,50 @@
 struct VertexInput {
     @location(0) position: vec3<f32>,
-    @location(1) color: vec3<f32>,
+    @location(1) color: vec4<f32>,
     @location(2) tex_coords: vec2<f32>,
 };
 
+struct QuadInput {
+    @location(0) rect: vec4<f32>,
+    @location(1) uv: vec4<f32>,
+    @location(2) color: vec4<f32>,
+    @location(3) flags: u32,
+    @location(4) style: u32,
+};
+
+// Must match QuadStyle
+struct QuadStyle {
+    radii: vec4<f32>,
+    border_color: vec4<f32>,
+    transform_x: vec2<f32>,
+    transform_y: vec2<f32>,
+    transform_offset: vec2<f32>,
+    stops: vec2<u32>,
+    border_width: f32,
+};
+
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
-    @location(1) color: vec3<f32>,
-    @location(2) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+
+    // With MSAA, an edge pixel would otherwise be shaded at its center, even
+    // if that's outside the triangle, and sample past the texture
+    @location(2) @interpolate(perspective, centroid) tex_coords: vec2<f32>,
+
+    // Only used by rounded rectangles
+    @location(3) @interpolate(flat) flags: u32,
+    @location(4) local: vec2<f32>,
+    @location(5) @interpolate(flat) half_size: vec2<f32>,
+    @location(6) @interpolate(flat) radii: vec4<f32>,
+    @location(7) @interpolate(flat) border_color: vec4<f32>,
+    @location(8) @interpolate(flat) border_width: f32,
+
+    // Only used by gradients and shadows
+    @location(9) @interpolate(flat) stops: vec2<u32>,
+    @location(10) @interpolate(flat) params: vec4<f32>,
+
+    // How many pixels on screen one unit of `local` is
+    @location(11) @interpolate(flat) scale: f32,
 };
 
 ///////////////////
@@ -22,17 +59,91 @@ struct ScreenSize {
 @group(0) @binding(0)
 var<uniform> screen_size: ScreenSize;
 
+@group(2) @binding(1)
+var<storage, read> styles: array<QuadStyle>;
+
+fn to_clip(position: vec3<f32>) -> vec4<f32> {
+    return vec4<f32>(
+        position.x / screen_size.w - 1.0,
+        -position.y / screen_size.h + 1.0,
+        position.z,
+        1.0
+    );
+}
+
 @vertex
 fn vs_main(model: VertexInput) -> VertexOutput {
     var out: VertexOutput;
-    out.clip_position = vec4<f32>(
-        model.position.x / screen_size.w - 1.0,
-        -model.position.y / screen_size.h + 1.0,
-        model.position.z,
-        1.0
-    );
+    out.clip_position = to_clip(model.position);
     out.tex_coords = model.tex_coords;
     out.color = model.color;
+    out.flags = 0u;
+    out.scale = 1.0;
+    return out;
+}
+
+// Must match the flags on Quad
+const SOLID: u32 = 1u;
+const ROUNDED: u32 = 2u;
+const GRADIENT: u32 = 4u;
+const RADIAL: u32 = 8u;
+const SHADOW: u32 = 16u;
+const PREMULTIPLIED: u32 = 32u;
+
+@vertex
+fn vs_quad(@builtin(vertex_index) index: u32, quad: QuadInput) -> VertexOutput {
+    // Two triangles, wound the same way as the vertices in Frame::triangles
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(0.0, 0.0),
+    );
+    let corner = corners[index];
+
+    let style = styles[quad.style];
+
+    var out: VertexOutput;
+    out.color = quad.color;
+    out.flags = quad.flags;
+    out.scale = sqrt(abs(determinant(mat2x2<f32>(style.transform_x, style.transform_y))));
+
+    // Leave a pixel around the shape for the anti-aliased edge,
+    // and room for the blur of a shadow
+    var grow = 0.0;
+    if ((quad.flags & ROUNDED) != 0u) {
+        grow = 1.0 / out.scale;
+    }
+    if ((quad.flags & SHADOW) != 0u) {
+        grow = 3.0 * quad.uv.x + 1.0 / out.scale;
+    }
+    let rect = quad.rect + vec4<f32>(-grow, -grow, grow, grow);
+
+    // Everything else is worked out before the transform
+    let position = mix(rect.xy, rect.zw, corner);
+    let screen = style.transform_x * position.x + style.transform_y * position.y + style.transform_offset;
+    out.clip_position = to_clip(vec3<f32>(screen, 0.0));
+
+    // The texture still lines up with the shape, past the edge we grew
+    let t = (position - quad.rect.xy) / (quad.rect.zw - quad.rect.xy);
+    out.tex_coords = mix(quad.uv.xy, quad.uv.zw, t);
+
+    // The fragment shader treats coordinates past the texture as a solid fill
+    if ((quad.flags & SOLID) != 0u) {
+        out.tex_coords = vec2<f32>(2.0, 2.0);
+    }
+
+    // Distances are measured from the center of the shape
+    out.half_size = (quad.rect.zw - quad.rect.xy) / 2.0;
+    out.local = position - (quad.rect.xy + out.half_size);
+    out.radii = min(style.radii, vec4<f32>(min(out.half_size.x, out.half_size.y)));
+    out.border_color = style.border_color;
+    out.border_width = style.border_width;
+    out.stops = style.stops;
+    out.params = quad.uv;
+
     return out;
 }
 
@@ -45,11 +156,108 @@ var t_diffuse: texture_2d<f32>;
 @group(1) @binding(1)
 var s_diffuse: sampler;
 
+struct Stop {
+    color: vec4<f32>,
+    offset: f32,
+};
+
+@group(2) @binding(0)
+var<storage, read> stops: array<Stop>;
+
+// Signed distance to the edge of a rectangle, with a radius for each corner
+// in the order top left, top right, bottom right, bottom left
+fn rounded_rect(p: vec2<f32>, half_size: vec2<f32>, radii: vec4<f32>) -> f32 {
+    var r = radii.x;
+    if (p.x >= 0.0 && p.y < 0.0) {
+        r = radii.y;
+    } else if (p.x >= 0.0 && p.y >= 0.0) {
+        r = radii.z;
+    } else if (p.x < 0.0 && p.y >= 0.0) {
+        r = radii.w;
+    }
+
+    let q = abs(p) - half_size + r;
+    return min(max(q.x, q.y), 0.0) + length(max(q, vec2<f32>(0.0))) - r;
+}
+
+// The pipeline blends premultiplied colors
+fn premultiply(color: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(color.rgb * color.a, color.a);
+}
+
+// The premultiplied color of the gradient at a point relative to the center
+fn gradient(p: vec2<f32>, flags: u32, geometry: vec4<f32>, range: vec2<u32>) -> vec4<f32> {
+    // How far along the gradient we are
+    var t: f32;
+    if ((flags & RADIAL) != 0u) {
+        t = length(p - geometry.xy) / max(geometry.z, 0.0001);
+    } else {
+        let line = geometry.zw - geometry.xy;
+        t = dot(p - geometry.xy, line) / max(dot(line, line), 0.0001);
+    }
+
+    // Every stop we've passed takes over from the ones before it
+    var color = premultiply(stops[range.x].color);
+    for (var i = range.x + 1u; i < range.x + range.y; i++) {
+        let start = stops[i - 1u].offset;
+        let end = stops[i].offset;
+        let s = clamp((t - start) / max(end - start, 0.0001), 0.0, 1.0);
+        color = mix(color, premultiply(stops[i].color), s);
+    }
+
+    return color;
+}
+
+// Close enough to the error function, from Abramowitz and Stegun
+fn erf(x: f32) -> f32 {
+    let a = abs(x);
+    let p = 1.0 + (0.278393 + (0.230389 + 0.078108 * a * a) * a) * a;
+    return sign(x) * (1.0 - 1.0 / (p * p * p * p));
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    if (in.tex_coords.x > 1.0) {
-        return vec4<f32>(in.color, 1.0);
+    let texel = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+
+    // Well past the texture is a solid fill, with the coverage in y. Edge
+    // pixels can land a little past the texture, and still sample it
+    let solid = in.tex_coords.x > 1.5;
+
+    // Which is either the color or the gradient
+    var fill = premultiply(in.color);
+    if ((in.flags & GRADIENT) != 0u) {
+        fill = gradient(in.local, in.flags, in.params, in.stops);
+    }
+    if (!solid) {
+        fill = texel * in.color;
+        if ((in.flags & PREMULTIPLIED) == 0u) {
+            fill = premultiply(fill);
+        }
+    }
+
+    if ((in.flags & SHADOW) != 0u) {
+        // A blurred edge is a step convolved with a gaussian
+        let d = rounded_rect(in.local, in.half_size, in.radii) * in.scale;
+        let sigma = max(in.params.x * in.scale, 0.5);
+        return fill * (0.5 - 0.5 * erf(d / (sigma * sqrt(2.0))));
+    }
+
+    if ((in.flags & ROUNDED) != 0u) {
+        // In pixels, so the edge stays as sharp when it's scaled
+        let d = rounded_rect(in.local, in.half_size, in.radii) * in.scale;
+
+        // Blend from the fill into the border, then out to nothing
+        var color = fill;
+        if (in.border_width > 0.0) {
+            let inside = clamp(0.5 - (d + in.border_width * in.scale), 0.0, 1.0);
+            color = mix(premultiply(in.border_color), fill, inside);
+        }
+        return color * clamp(0.5 - d, 0.0, 1.0);
+    }
+
+    if (solid) {
+        return fill * min(in.tex_coords.y, 1.0);
     }
 
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords) * vec4<f32>(in.color, 1.0);
+    return fill;
 }
@@ -0,0 +1,477 @@
+use std::{collections::HashMap, f32::consts::SQRT_2};
+
+use winit::dpi::PhysicalSize;
+
+use super::{
+    blur::blur_kernel,
+    color::{from_srgb, to_srgb},
+    texture::{TextureData, TextureId},
+    vertex::{Quad, QuadStyle, Stop, Vertex},
+    Frame, Primitive, Transform, Vec2,
+};
+
+/// Rasterizes frames on the CPU, following the same rules as `shader.wgsl`
+/// and the render pipeline, for machines where wgpu has no adapter.
+pub struct Software {
+    pub size: PhysicalSize<u32>,
+
+    // Kept between frames, just like the texture bind groups
+    textures: HashMap<TextureId, TextureData>,
+    blank: TextureData,
+
+    // The texture the current batch samples
+    texture: Option<TextureId>,
+
+    // Where backdrops are blurred into
+    blurred: TextureId,
+
+    // Premultiplied linear RGBA, blended the same way the sRGB render target would be
+    pixels: Vec<[f32; 4]>,
+
+    // The pixels the current batch is clipped to
+    scissor: [u32; 4],
+
+    // The quad styles and gradient stops of the frame being drawn
+    styles: Vec<QuadStyle>,
+    stops: Vec<Stop>,
+}
+
+impl Software {
+    pub fn new(size: PhysicalSize<u32>) -> Software {
+        return Software {
+            size,
+            textures: HashMap::new(),
+            blank: TextureData::blank(),
+            texture: None,
+            blurred: TextureId::unique(),
+            pixels: vec![],
+            scissor: [0, 0, size.width, size.height],
+            styles: vec![],
+            stops: vec![],
+        };
+    }
+
+    pub fn render(&mut self, mut frame: Frame) -> TextureData {
+        frame.sort_batches();
+
+        for id in &frame.removed_textures {
+            self.textures.remove(id);
+        }
+        self.textures.extend(frame.textures);
+        self.styles = frame.styles;
+        self.stops = frame.stops;
+
+        // Clear the screen
+        let (width, height) = (self.size.width, self.size.height);
+        self.pixels = vec![frame.clear_color.premultiplied(); (width * height) as usize];
+
+        for batch in &frame.batches {
+            self.scissor = batch.scissor(self.size);
+            self.texture = batch.texture;
+
+            match &batch.primitive {
+                Primitive::Triangles(indices) => {
+                    let indices = &frame.indices[indices.start as usize..indices.end as usize];
+                    for triangle in indices.chunks_exact(3) {
+                        let [a, b, c] = [0, 1, 2].map(|i| &frame.vertices[triangle[i] as usize]);
+                        self.triangle(a, b, c);
+                    }
+                }
+                Primitive::Quads(quads) => {
+                    for quad in &frame.quads[quads.start as usize..quads.end as usize] {
+                        self.quad(quad);
+                    }
+                }
+                Primitive::Backdrop { quad, blur } => {
+                    self.backdrop(&frame.quads[*quad as usize], *blur);
+                }
+            }
+        }
+
+        // Store it the way an sRGB texture would
+        let bytes = self
+            .pixels
+            .iter()
+            .flat_map(|p| {
+                [
+                    to_srgb(p[0]),
+                    to_srgb(p[1]),
+                    to_srgb(p[2]),
+                    (p[3] * 255.).round() as u8,
+                ]
+            })
+            .collect();
+
+        return TextureData::new(bytes, (width, height));
+    }
+
+    fn triangle(&mut self, a: &Vertex, b: &Vertex, c: &Vertex) {
+        let area = edge(a, b, c.position[0], c.position[1]);
+
+        // Nothing is culled, so wind everything the same way
+        if area > 0 {
+            return self.triangle(a, c, b);
+        }
+        if area == 0 {
+            return;
+        }
+
+        // Only visit the pixels the triangle could touch
+        let xs = [a.position[0], b.position[0], c.position[0]];
+        let ys = [a.position[1], b.position[1], c.position[1]];
+        let [sx0, sy0, sx1, sy1] = self.scissor;
+        let x0 = (xs.iter().copied().fold(f32::MAX, f32::min).floor().max(0.) as u32).max(sx0);
+        let y0 = (ys.iter().copied().fold(f32::MAX, f32::min).floor().max(0.) as u32).max(sy0);
+        let x1 = (xs.iter().copied().fold(f32::MIN, f32::max).ceil() as u32).min(sx1);
+        let y1 = (ys.iter().copied().fold(f32::MIN, f32::max).ceil() as u32).min(sy1);
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                // Sample at the pixel center
+                let px = x as f32 + 0.5;
+                let py = y as f32 + 0.5;
+
+                if !covers(edge(b, c, px, py), b, c)
+                    || !covers(edge(c, a, px, py), c, a)
+                    || !covers(edge(a, b, px, py), a, b)
+                {
+                    continue;
+                }
+
+                // Barycentric weights, from the positions before they were snapped
+                let area = weight(a, b, c.position[0], c.position[1]);
+                let wa = weight(b, c, px, py) / area;
+                let wb = weight(c, a, px, py) / area;
+                let wc = weight(a, b, px, py) / area;
+                let lerp = |f: fn(&Vertex) -> f32| f(a) * wa + f(b) * wb + f(c) * wc;
+
+                let color = [
+                    lerp(|v| v.color[0]),
+                    lerp(|v| v.color[1]),
+                    lerp(|v| v.color[2]),
+                    lerp(|v| v.color[3]),
+                ];
+                let tex_coords = [lerp(|v| v.tex_coords[0]), lerp(|v| v.tex_coords[1])];
+
+                let src = self.fragment(color, tex_coords);
+                self.blend(x, y, src);
+            }
+        }
+    }
+
+    fn quad(&mut self, quad: &Quad) {
+        let solid = quad.flags & Quad::SOLID != 0;
+        let style = self.styles[quad.style as usize];
+
+        // Leave a pixel around the shape for the anti-aliased edge,
+        // and room for the blur of a shadow, like `vs_quad`
+        let transform = Transform::from_array(style.transform);
+        let scale = transform.scale_factor();
+        let mut grow = 0.;
+        if quad.flags & Quad::ROUNDED != 0 {
+            grow = 1. / scale;
+        }
+        if quad.flags & Quad::SHADOW != 0 {
+            grow = 3. * quad.uv[0] + 1. / scale;
+        }
+        let [x0, y0, x1, y1] = quad.rect;
+        let [ex0, ey0, ex1, ey1] = [x0 - grow, y0 - grow, x1 + grow, y1 + grow];
+
+        // The same two triangles as `vs_quad`, on screen
+        let corners = [[ex0, ey0], [ex0, ey1], [ex1, ey1], [ex1, ey0]].map(|[x, y]| {
+            let p = transform.apply(Vec2::new(x, y));
+            Vertex {
+                position: [p.x, p.y, 0.],
+                color: [0.; 4],
+                tex_coords: [0.; 2],
+            }
+        });
+        let [a, b, c, d] = &corners;
+        let mut pixels = self.covered(a, b, c);
+        pixels.extend(self.covered(c, d, a));
+
+        // Shading happens before the transform, in the space of the rect
+        let inverse = transform.inverse();
+
+        let half_size = [(x1 - x0) / 2., (y1 - y0) / 2.];
+        let radii = style.radii.map(|r| r.min(half_size[0].min(half_size[1])));
+
+        for (x, y) in pixels {
+            let p = inverse.apply(Vec2::new(x as f32 + 0.5, y as f32 + 0.5));
+            let (px, py) = (p.x, p.y);
+
+            let local = [px - (x0 + half_size[0]), py - (y0 + half_size[1])];
+
+            // Solid fills are either the color or the gradient
+            let mut fill = match quad.flags & Quad::GRADIENT != 0 {
+                true => self.gradient(local, quad, &style),
+                false => premultiply(quad.color),
+            };
+
+            if !solid {
+                // The texture lines up with the shape, not the edge we grew
+                let tx = (px - x0) / (x1 - x0);
+                let ty = (py - y0) / (y1 - y0);
+                let [u0, v0, u1, v1] = quad.uv;
+                let texel = self.sample([u0 + (u1 - u0) * tx, v0 + (v1 - v0) * ty]);
+
+                fill = [0, 1, 2, 3].map(|i| texel[i] * quad.color[i]);
+                if quad.flags & Quad::PREMULTIPLIED == 0 {
+                    fill = premultiply(fill);
+                }
+            }
+
+            let src = if quad.flags & Quad::SHADOW != 0 {
+                // A blurred edge is a step convolved with a gaussian
+                let d = rounded_rect(local, half_size, radii) * scale;
+                let sigma = (quad.uv[0] * scale).max(0.5);
+                let coverage = 0.5 - 0.5 * erf(d / (sigma * SQRT_2));
+                fill.map(|c| c * coverage)
+            } else if quad.flags & Quad::ROUNDED != 0 {
+                // In pixels, so the edge stays as sharp when it's scaled
+                let d = rounded_rect(local, half_size, radii) * scale;
+
+                // Blend from the fill into the border, then out to nothing
+                let mut color = fill;
+                if style.border_width > 0. {
+                    let inside = (0.5 - (d + style.border_width * scale)).clamp(0., 1.);
+                    let border = premultiply(style.border_color);
+                    color = [0, 1, 2, 3].map(|i| border[i] * (1. - inside) + fill[i] * inside);
+                }
+
+                let coverage = (0.5 - d).clamp(0., 1.);
+                color.map(|c| c * coverage)
+            } else {
+                fill
+            };
+
+            self.blend(x, y, src);
+        }
+    }
+
+    // The pixels inside the triangle, with the same rules as `triangle`
+    fn covered(&self, a: &Vertex, b: &Vertex, c: &Vertex) -> Vec<(u32, u32)> {
+        let area = edge(a, b, c.position[0], c.position[1]);
+        if area > 0 {
+            return self.covered(a, c, b);
+        }
+        if area == 0 {
+            return vec![];
+        }
+
+        let xs = [a.position[0], b.position[0], c.position[0]];
+        let ys = [a.position[1], b.position[1], c.position[1]];
+        let [sx0, sy0, sx1, sy1] = self.scissor;
+        let x0 = (xs.iter().copied().fold(f32::MAX, f32::min).floor().max(0.) as u32).max(sx0);
+        let y0 = (ys.iter().copied().fold(f32::MAX, f32::min).floor().max(0.) as u32).max(sy0);
+        let x1 = (xs.iter().copied().fold(f32::MIN, f32::max).ceil().max(0.) as u32).min(sx1);
+        let y1 = (ys.iter().copied().fold(f32::MIN, f32::max).ceil().max(0.) as u32).min(sy1);
+
+        let mut pixels = vec![];
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let px = x as f32 + 0.5;
+                let py = y as f32 + 0.5;
+
+                if covers(edge(b, c, px, py), b, c)
+                    && covers(edge(c, a, px, py), c, a)
+                    && covers(edge(a, b, px, py), a, b)
+                {
+                    pixels.push((x, y));
+                }
+            }
+        }
+
+        return pixels;
+    }
+
+    // Same as the blur passes in `Painter`
+    fn backdrop(&mut self, quad: &Quad, blur: f32) {
+        let (w, h) = (self.size.width as i32, self.size.height as i32);
+        let at = |x: i32, y: i32| (y.clamp(0, h - 1) * w + x.clamp(0, w - 1)) as usize;
+
+        // Only blur under the quad, along with its anti-aliased edge
+        let [x0, y0, x1, y1] = quad.bounds(&self.styles);
+        let x0 = ((x0 - 1.).floor() as i32).clamp(0, w);
+        let y0 = ((y0 - 1.).floor() as i32).clamp(0, h);
+        let x1 = ((x1 + 1.).ceil() as i32).clamp(0, w);
+        let y1 = ((y1 + 1.).ceil() as i32).clamp(0, h);
+
+        let (radius, weights) = blur_kernel(blur);
+        let taps = || (-radius..=radius).zip(weights.iter());
+
+        // Across, including the rows the second pass reaches
+        let mut across = vec![[0.; 4]; (w * h) as usize];
+        for y in (y0 - radius).max(0)..(y1 + radius).min(h) {
+            for x in x0..x1 {
+                let mut sum = [0.; 4];
+                for (i, weight) in taps() {
+                    let p = self.pixels[at(x + i, y)];
+                    sum = [0, 1, 2, 3].map(|c| sum[c] + p[c] * weight);
+                }
+                across[at(x, y)] = sum;
+            }
+        }
+
+        // Then down, into an sRGB texture just like the GPU blurs into
+        let mut bytes = vec![0; (w * h * 4) as usize];
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let mut sum = [0.; 4];
+                for (i, weight) in taps() {
+                    let p = across[at(x, y + i)];
+                    sum = [0, 1, 2, 3].map(|c| sum[c] + p[c] * weight);
+                }
+
+                let i = at(x, y) * 4;
+                bytes[i..i + 4].copy_from_slice(&[
+                    to_srgb(sum[0]),
+                    to_srgb(sum[1]),
+                    to_srgb(sum[2]),
+                    (sum[3] * 255.).round() as u8,
+                ]);
+            }
+        }
+
+        // And draw it back through the quad
+        let texture = TextureData::new(bytes, (w as u32, h as u32));
+        self.textures.insert(self.blurred, texture);
+        self.texture = Some(self.blurred);
+        self.quad(quad);
+    }
+
+    // Same as `gradient` in the shader
+    fn gradient(&self, p: [f32; 2], quad: &Quad, style: &QuadStyle) -> [f32; 4] {
+        let [gx0, gy0, gx1, gy1] = quad.uv;
+
+        // How far along the gradient we are
+        let t = if quad.flags & Quad::RADIAL != 0 {
+            (p[0] - gx0).hypot(p[1] - gy0) / gx1.max(0.0001)
+        } else {
+            let line = [gx1 - gx0, gy1 - gy0];
+            let along = (p[0] - gx0) * line[0] + (p[1] - gy0) * line[1];
+            along / (line[0] * line[0] + line[1] * line[1]).max(0.0001)
+        };
+
+        // Every stop we've passed takes over from the ones before it
+        let [first, count] = style.stops.map(|i| i as usize);
+        let stops = &self.stops[first..first + count];
+
+        let mut color = premultiply(stops[0].color);
+        for pair in stops.windows(2) {
+            let (start, end) = (pair[0].offset, pair[1].offset);
+            let s = ((t - start) / (end - start).max(0.0001)).clamp(0., 1.);
+            let next = premultiply(pair[1].color);
+            color = [0, 1, 2, 3].map(|i| color[i] * (1. - s) + next[i] * s);
+        }
+
+        return color;
+    }
+
+    fn fragment(&self, color: [f32; 4], tex_coords: [f32; 2]) -> [f32; 4] {
+        // Solid fill, with the coverage in y
+        if tex_coords[0] > 1.5 {
+            let coverage = tex_coords[1].min(1.0);
+            return premultiply(color).map(|c| c * coverage);
+        }
+
+        let texel = self.sample(tex_coords);
+        return premultiply([0, 1, 2, 3].map(|i| texel[i] * color[i]));
+    }
+
+    fn sample(&self, tex_coords: [f32; 2]) -> [f32; 4] {
+        let texture = self
+            .texture
+            .and_then(|id| self.textures.get(&id))
+            .unwrap_or(&self.blank);
+
+        // The default sampler is nearest + clamp to edge
+        let (w, h) = texture.dimensions;
+        let x = ((tex_coords[0] * w as f32).floor().max(0.) as u32).min(w - 1);
+        let y = ((tex_coords[1] * h as f32).floor().max(0.) as u32).min(h - 1);
+
+        let i = ((y * w + x) * 4) as usize;
+        let texel = &texture.bytes[i..i + 4];
+
+        return [
+            from_srgb(texel[0]),
+            from_srgb(texel[1]),
+            from_srgb(texel[2]),
+            texel[3] as f32 / 255.,
+        ];
+    }
+
+    fn blend(&mut self, x: u32, y: u32, src: [f32; 4]) {
+        // BlendState::PREMULTIPLIED_ALPHA_BLENDING
+        let dst = &mut self.pixels[(y * self.size.width + x) as usize];
+        let a = src[3];
+
+        for i in 0..4 {
+            dst[i] = src[i] + dst[i] * (1. - a);
+        }
+    }
+}
+
+// Same as `erf` in the shader
+fn erf(x: f32) -> f32 {
+    let a = x.abs();
+    let p = 1. + (0.278393 + (0.230389 + 0.078108 * a * a) * a) * a;
+    return x.signum() * (1. - 1. / (p * p * p * p));
+}
+
+// Same as `premultiply` in the shader
+fn premultiply(color: [f32; 4]) -> [f32; 4] {
+    let a = color[3];
+    return [color[0] * a, color[1] * a, color[2] * a, a];
+}
+
+// Same as `rounded_rect` in the shader
+fn rounded_rect(p: [f32; 2], half_size: [f32; 2], radii: [f32; 4]) -> f32 {
+    let r = match (p[0] >= 0., p[1] >= 0.) {
+        (false, false) => radii[0],
+        (true, false) => radii[1],
+        (true, true) => radii[2],
+        (false, true) => radii[3],
+    };
+
+    let qx = p[0].abs() - half_size[0] + r;
+    let qy = p[1].abs() - half_size[1] + r;
+    return qx.max(qy).min(0.) + qx.max(0.).hypot(qy.max(0.)) - r;
+}
+
+// Positions are snapped to this many steps per pixel, like GPUs do, so edges
+// are worked out exactly and come out the same for both triangles sharing them
+const SUBPIXELS: f32 = 256.;
+
+// Far enough off screen for anything, while keeping `edge` from overflowing
+const MAX_SUBPIXEL: f32 = (1 << 28) as f32;
+
+fn snap(v: f32) -> i64 {
+    return (v * SUBPIXELS).round().clamp(-MAX_SUBPIXEL, MAX_SUBPIXEL) as i64;
+}
+
+// Twice the signed area of the triangle (a, b, p), in square subpixels
+fn edge(a: &Vertex, b: &Vertex, px: f32, py: f32) -> i64 {
+    let (ax, ay) = (snap(a.position[0]), snap(a.position[1]));
+    let (bx, by) = (snap(b.position[0]), snap(b.position[1]));
+    let (px, py) = (snap(px), snap(py));
+
+    return (bx - ax) * (py - ay) - (by - ay) * (px - ax);
+}
+
+// Like `edge`, without snapping, for interpolating across the triangle
+fn weight(a: &Vertex, b: &Vertex, px: f32, py: f32) -> f32 {
+    return (b.position[0] - a.position[0]) * (py - a.position[1])
+        - (b.position[1] - a.position[1]) * (px - a.position[0]);
+}
+
+// Top-left fill rule, so shared edges are only drawn once
+fn covers(w: i64, a: &Vertex, b: &Vertex) -> bool {
+    if w != 0 {
+        return w < 0;
+    }
+
+    let dx = snap(b.position[0]) - snap(a.position[0]);
+    let dy = snap(b.position[1]) - snap(a.position[1]);
+    return dy > 0 || (dy == 0 && dx < 0);
+}
@@ -0,0 +1,247 @@
+use std::f32::consts::PI;
+
+use super::{Color, Vec2, Vertex};
+
+// Longest a miter can get, relative to the width, before we bevel instead
+const MITER_LIMIT: f32 = 4.;
+
+// Round joins and caps are allowed to be this far off the true circle
+const TOLERANCE: f32 = 0.25;
+
+/// How two segments of a polyline meet.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Join {
+    Miter,
+    Round,
+    Bevel,
+}
+
+/// How the ends of a line are finished.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cap {
+    Butt,
+    Round,
+    Square,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Stroke {
+    pub width: f32,
+    pub color: Color,
+    pub join: Join,
+    pub cap: Cap,
+}
+
+impl Stroke {
+    #[allow(dead_code)]
+    pub fn new(width: f32, color: Color) -> Stroke {
+        return Stroke {
+            width,
+            color,
+            join: Join::Miter,
+            cap: Cap::Butt,
+        };
+    }
+}
+
+/// Turns a polyline into triangles.
+///
+/// Every edge gets a one pixel fringe that fades out, which is what makes
+/// the line anti-aliased. The coverage is passed to the shader as the second
+/// texture coordinate of a solid fill. The points are transformed after
+/// this, so `scale` is how many pixels on screen one unit ends up as.
+pub fn tessellate(points: &[Vec2], stroke: &Stroke, scale: f32) -> (Vec<Vertex>, Vec<u32>) {
+    // Half a pixel either side of the edge
+    let half_pixel = 0.5 / scale;
+
+    let mut tess = Tessellator {
+        color: stroke.color.to_array(),
+        inner: (stroke.width / 2. - half_pixel).max(0.),
+        outer: stroke.width / 2. + half_pixel,
+        coverage: (stroke.width * scale).min(1.),
+        scale,
+        vertices: vec![],
+        indices: vec![],
+    };
+
+    // Repeated points don't have a direction
+    let mut points = points.to_vec();
+    points.dedup_by(|a, b| (*a - *b).length() < 1e-4);
+
+    if points.len() < 2 {
+        return (tess.vertices, tess.indices);
+    }
+
+    let dirs: Vec<Vec2> = points
+        .windows(2)
+        .map(|p| (p[1] - p[0]).normalize())
+        .collect();
+
+    let half = stroke.width / 2.;
+    let first = points[0];
+    let last = points[points.len() - 1];
+
+    // Start cap
+    let (d, n) = (dirs[0], dirs[0].perp());
+    let mut prev = match stroke.cap {
+        Cap::Butt | Cap::Square => {
+            let start = if stroke.cap == Cap::Square { first - d * half } else { first };
+            let fringe = tess.station(start - d * half_pixel, n, -n, true);
+            let station = tess.station(start, n, -n, false);
+            tess.connect(fringe, station);
+            station
+        }
+        Cap::Round => {
+            tess.fan(first, n, PI);
+            tess.station(first, n, -n, false)
+        }
+    };
+
+    // Joins
+    for i in 1..points.len() - 1 {
+        for (l, r) in join(dirs[i - 1], dirs[i], stroke.join, tess.outer * scale) {
+            let station = tess.station(points[i], l, r, false);
+            tess.connect(prev, station);
+            prev = station;
+        }
+    }
+
+    // End cap
+    let d = dirs[dirs.len() - 1];
+    let n = d.perp();
+    match stroke.cap {
+        Cap::Butt | Cap::Square => {
+            let end = if stroke.cap == Cap::Square { last + d * half } else { last };
+            let station = tess.station(end, n, -n, false);
+            let fringe = tess.station(end + d * half_pixel, n, -n, true);
+            tess.connect(prev, station);
+            tess.connect(station, fringe);
+        }
+        Cap::Round => {
+            let station = tess.station(last, n, -n, false);
+            tess.connect(prev, station);
+            tess.fan(last, -n, PI);
+        }
+    }
+
+    return (tess.vertices, tess.indices);
+}
+
+/// The offsets (left, right) to place at the point where two segments meet.
+/// The radius is in pixels on screen.
+fn join(a: Vec2, b: Vec2, kind: Join, radius: f32) -> Vec<(Vec2, Vec2)> {
+    let (na, nb) = (a.perp(), b.perp());
+
+    // Where the two edges would meet, as a multiple of the half width
+    let bisector = na + nb;
+    if bisector.length() < 1e-4 {
+        // The line doubles back on itself
+        return vec![(na, -na), (nb, -nb)];
+    }
+    let bisector = bisector.normalize();
+    let miter = bisector * (1. / bisector.dot(na));
+
+    // Going straight on
+    if a.cross(b).abs() < 1e-4 || (kind == Join::Miter && miter.length() <= MITER_LIMIT) {
+        return vec![(miter, -miter)];
+    }
+
+    // Which way are we turning? The inside of the turn stays on the miter
+    // so the triangles don't overlap, the outside is cut off or rounded.
+    let turns_left = a.cross(b) < 0.;
+    let inner = miter * (MITER_LIMIT.min(miter.length()) / miter.length());
+    let (from, to) = if turns_left { (na, nb) } else { (-na, -nb) };
+
+    let steps = match kind {
+        Join::Round => {
+            let angle = from.dot(to).clamp(-1., 1.).acos();
+            let step = 2. * (1. - TOLERANCE / radius).max(-1.).acos();
+            (angle / step).ceil().max(1.) as usize
+        }
+        _ => 1,
+    };
+
+    let sweep = from.cross(to).atan2(from.dot(to));
+    return (0..=steps)
+        .map(|i| from.rotate(sweep * i as f32 / steps as f32))
+        .map(|outer| match turns_left {
+            true => (outer, -inner),
+            false => (inner, outer),
+        })
+        .collect();
+}
+
+struct Tessellator {
+    color: [f32; 4],
+
+    // Offsets from the middle of the line, where it's fully covered and
+    // where it has completely faded out
+    inner: f32,
+    outer: f32,
+    coverage: f32,
+
+    // Pixels on screen to one unit
+    scale: f32,
+
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+}
+
+impl Tessellator {
+    fn vertex(&mut self, position: Vec2, coverage: f32) -> u32 {
+        self.vertices.push(Vertex {
+            position: [position.x, position.y, 0.],
+            color: self.color,
+            tex_coords: [2., coverage],
+        });
+
+        return self.vertices.len() as u32 - 1;
+    }
+
+    /// A cross section of the line: the fringe, the middle and the fringe
+    /// on the other side. Returns the index of the first vertex.
+    fn station(&mut self, p: Vec2, left: Vec2, right: Vec2, fringe: bool) -> u32 {
+        let coverage = if fringe { 0. } else { self.coverage };
+
+        let first = self.vertex(p + left * self.outer, 0.);
+        self.vertex(p + left * self.inner, coverage);
+        self.vertex(p + right * self.inner, coverage);
+        self.vertex(p + right * self.outer, 0.);
+
+        return first;
+    }
+
+    /// Fills in the line between two cross sections.
+    fn connect(&mut self, a: u32, b: u32) {
+        for i in 0..3 {
+            let (a0, a1, b0, b1) = (a + i, a + i + 1, b + i, b + i + 1);
+            self.indices.extend_from_slice(&[a0, b0, b1, a0, b1, a1]);
+        }
+    }
+
+    /// A rounded cap, starting at the offset `from` and sweeping `angle`
+    /// radians around the center.
+    fn fan(&mut self, center: Vec2, from: Vec2, angle: f32) {
+        let step = 2. * (1. - TOLERANCE / (self.outer * self.scale)).max(-1.).acos();
+        let steps = (angle / step).ceil().max(2.) as usize;
+
+        let middle = self.vertex(center, self.coverage);
+        let mut prev = None;
+
+        for i in 0..=steps {
+            let dir = from.rotate(angle * i as f32 / steps as f32);
+            let inner = self.vertex(center + dir * self.inner, self.coverage);
+            let outer = self.vertex(center + dir * self.outer, 0.);
+
+            if let Some((prev_inner, prev_outer)) = prev {
+                self.indices.extend_from_slice(&[middle, prev_inner, inner]);
+                self.indices
+                    .extend_from_slice(&[prev_inner, prev_outer, outer, prev_inner, outer, inner]);
+            }
+
+            prev = Some((inner, outer));
+        }
+    }
+}
@@ -1,5 +1,78 @@
+use std::{
+    error::Error,
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter},
+    path::Path,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
 use wgpu::*;
 
+/// Names a texture, so draws can say which one they sample.
+/// The pixels are uploaded separately, with `Frame::set_texture`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureId(u32);
+
+impl TextureId {
+    /// An id that no other texture has.
+    pub fn unique() -> TextureId {
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+        return TextureId(NEXT.fetch_add(1, Ordering::Relaxed));
+    }
+}
+
+/// Why an image couldn't be loaded.
+#[derive(Debug)]
+pub enum ImageError {
+    Io(io::Error),
+    Png(png::DecodingError),
+    Jpeg(jpeg_decoder::Error),
+
+    /// Neither a PNG nor a JPEG, or a kind of one we can't turn into RGBA
+    Unsupported,
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            ImageError::Io(err) => write!(f, "couldn't read the image: {}", err),
+            ImageError::Png(err) => write!(f, "couldn't decode the PNG: {}", err),
+            ImageError::Jpeg(err) => write!(f, "couldn't decode the JPEG: {}", err),
+            ImageError::Unsupported => write!(f, "not a PNG or JPEG that can be decoded"),
+        };
+    }
+}
+
+impl Error for ImageError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        return match self {
+            ImageError::Io(err) => Some(err),
+            ImageError::Png(err) => Some(err),
+            ImageError::Jpeg(err) => Some(err),
+            ImageError::Unsupported => None,
+        };
+    }
+}
+
+impl From<io::Error> for ImageError {
+    fn from(err: io::Error) -> Self {
+        return ImageError::Io(err);
+    }
+}
+
+impl From<png::DecodingError> for ImageError {
+    fn from(err: png::DecodingError) -> Self {
+        return ImageError::Png(err);
+    }
+}
+
+impl From<jpeg_decoder::Error> for ImageError {
+    fn from(err: jpeg_decoder::Error) -> Self {
+        return ImageError::Jpeg(err);
+    }
+}
+
 pub struct TextureData {
     pub bytes: Vec<u8>,
     pub dimensions: (u32, u32),
@@ -16,6 +89,90 @@ impl TextureData {
             dimensions: (1, 1),
         };
     }
+
+    /// Reads a PNG or JPEG file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ImageError> {
+        return TextureData::decode(&fs::read(path)?);
+    }
+
+    /// Decodes a PNG or JPEG, telling them apart by their first bytes.
+    pub fn decode(bytes: &[u8]) -> Result<Self, ImageError> {
+        if bytes.starts_with(b"\x89PNG") {
+            return decode_png(bytes);
+        }
+
+        if bytes.starts_with(&[0xff, 0xd8]) {
+            return decode_jpeg(bytes);
+        }
+
+        return Err(ImageError::Unsupported);
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), png::EncodingError> {
+        let file = BufWriter::new(File::create(path)?);
+
+        let mut encoder = png::Encoder::new(file, self.dimensions.0, self.dimensions.1);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        return encoder.write_header()?.write_image_data(&self.bytes);
+    }
+}
+
+fn decode_png(bytes: &[u8]) -> Result<TextureData, ImageError> {
+    let mut decoder = png::Decoder::new(bytes);
+
+    // Always give us 8 bits per channel, with palettes expanded
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels)?;
+    pixels.truncate(info.buffer_size());
+
+    let bytes = match info.color_type {
+        png::ColorType::Rgba => pixels,
+        png::ColorType::Rgb => rgba(&pixels, 3, |p| [p[0], p[1], p[2], 0xff]),
+        png::ColorType::GrayscaleAlpha => rgba(&pixels, 2, |p| [p[0], p[0], p[0], p[1]]),
+        png::ColorType::Grayscale => rgba(&pixels, 1, |p| [p[0], p[0], p[0], 0xff]),
+        png::ColorType::Indexed => return Err(ImageError::Unsupported),
+    };
+
+    return Ok(TextureData::new(bytes, (info.width, info.height)));
+}
+
+fn decode_jpeg(bytes: &[u8]) -> Result<TextureData, ImageError> {
+    let mut decoder = jpeg_decoder::Decoder::new(bytes);
+    let pixels = decoder.decode()?;
+    let info = decoder.info().ok_or(ImageError::Unsupported)?;
+
+    use jpeg_decoder::PixelFormat;
+    let bytes = match info.pixel_format {
+        PixelFormat::RGB24 => rgba(&pixels, 3, |p| [p[0], p[1], p[2], 0xff]),
+        PixelFormat::L8 => rgba(&pixels, 1, |p| [p[0], p[0], p[0], 0xff]),
+        // Big endian, so the first byte is the most significant
+        PixelFormat::L16 => rgba(&pixels, 2, |p| [p[0], p[0], p[0], 0xff]),
+        PixelFormat::CMYK32 => rgba(&pixels, 4, |p| {
+            let k = p[3] as u16;
+            let channel = |c: u8| (255 - c as u16) * (255 - k) / 255;
+            [
+                channel(p[0]) as u8,
+                channel(p[1]) as u8,
+                channel(p[2]) as u8,
+                0xff,
+            ]
+        }),
+    };
+
+    return Ok(TextureData::new(
+        bytes,
+        (info.width as u32, info.height as u32),
+    ));
+}
+
+// Converts every pixel of `size` bytes to RGBA
+fn rgba(pixels: &[u8], size: usize, convert: impl Fn(&[u8]) -> [u8; 4]) -> Vec<u8> {
+    return pixels.chunks_exact(size).flat_map(convert).collect();
 }
 
 pub struct Texture {
@@ -23,7 +180,7 @@ pub struct Texture {
     pub sampler: Sampler,
 }
 
-pub fn create_texture(device: &Device, queue: &Queue, data: TextureData) -> Texture {
+pub fn create_texture(device: &Device, queue: &Queue, data: &TextureData) -> Texture {
     let texture_size = Extent3d {
         width: data.dimensions.0,
         height: data.dimensions.1,
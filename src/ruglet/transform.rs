@@ -0,0 +1,137 @@
+use super::{Area, Vec2};
+
+/// A 2D affine transform: where the x and y axes end up, and how far
+/// everything gets moved afterwards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub x: Vec2,
+    pub y: Vec2,
+    pub offset: Vec2,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        x: Vec2 { x: 1., y: 0. },
+        y: Vec2 { x: 0., y: 1. },
+        offset: Vec2 { x: 0., y: 0. },
+    };
+
+    #[allow(dead_code)]
+    pub fn translate(offset: Vec2) -> Transform {
+        return Transform {
+            offset,
+            ..Transform::IDENTITY
+        };
+    }
+
+    pub fn scale(x: f32, y: f32) -> Transform {
+        return Transform {
+            x: Vec2::new(x, 0.),
+            y: Vec2::new(0., y),
+            ..Transform::IDENTITY
+        };
+    }
+
+    /// Turns clockwise on screen, around the origin.
+    #[allow(dead_code)]
+    pub fn rotate(angle: f32) -> Transform {
+        return Transform {
+            x: Vec2::new(1., 0.).rotate(angle),
+            y: Vec2::new(0., 1.).rotate(angle),
+            ..Transform::IDENTITY
+        };
+    }
+
+    /// Scales or rotates around a point instead of the origin.
+    #[allow(dead_code)]
+    pub fn around(self, point: Vec2) -> Transform {
+        return Transform::translate(point)
+            .then(self)
+            .then(Transform::translate(-point));
+    }
+
+    /// Applies `other` first, and then this one.
+    pub fn then(self, other: Transform) -> Transform {
+        return Transform {
+            x: self.vector(other.x),
+            y: self.vector(other.y),
+            offset: self.apply(other.offset),
+        };
+    }
+
+    pub fn apply(&self, p: Vec2) -> Vec2 {
+        return self.vector(p) + self.offset;
+    }
+
+    /// Like `apply`, but directions don't get moved.
+    pub fn vector(&self, v: Vec2) -> Vec2 {
+        return self.x * v.x + self.y * v.y;
+    }
+
+    /// Undoes the transform. Flattened transforms can't be undone, so
+    /// those give back something that isn't a number.
+    pub fn inverse(&self) -> Transform {
+        let det = self.x.cross(self.y);
+        let x = Vec2::new(self.y.y, -self.x.y) * (1. / det);
+        let y = Vec2::new(-self.y.x, self.x.x) * (1. / det);
+
+        let inverse = Transform {
+            x,
+            y,
+            offset: Vec2::zero(),
+        };
+        return Transform {
+            offset: -inverse.vector(self.offset),
+            ..inverse
+        };
+    }
+
+    /// How much the transform grows lengths, on average.
+    pub fn scale_factor(&self) -> f32 {
+        return self.x.cross(self.y).abs().sqrt();
+    }
+
+    /// The smallest axis aligned area the transformed area fits in.
+    pub fn bounds(&self, area: Area) -> Area {
+        let corners = [
+            area.0,
+            Vec2::new(area.1.x, area.0.y),
+            area.1,
+            Vec2::new(area.0.x, area.1.y),
+        ]
+        .map(|p| self.apply(p));
+
+        let xs = corners.map(|p| p.x);
+        let ys = corners.map(|p| p.y);
+        return Area(
+            Vec2::new(
+                xs.iter().copied().fold(f32::MAX, f32::min),
+                ys.iter().copied().fold(f32::MAX, f32::min),
+            ),
+            Vec2::new(
+                xs.iter().copied().fold(f32::MIN, f32::max),
+                ys.iter().copied().fold(f32::MIN, f32::max),
+            ),
+        );
+    }
+
+    /// Packed the way `QuadStyle.transform` expects it.
+    pub fn to_array(self) -> [f32; 6] {
+        return [
+            self.x.x,
+            self.x.y,
+            self.y.x,
+            self.y.y,
+            self.offset.x,
+            self.offset.y,
+        ];
+    }
+
+    pub fn from_array(m: [f32; 6]) -> Transform {
+        return Transform {
+            x: Vec2::new(m[0], m[1]),
+            y: Vec2::new(m[2], m[3]),
+            offset: Vec2::new(m[4], m[5]),
+        };
+    }
+}
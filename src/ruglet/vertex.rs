@@ -1,10 +1,12 @@
 use wgpu::*;
 
+use super::{Area, Transform, Vec2};
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
 pub struct Vertex {
     pub position: [f32; 3],
-    pub color: [f32; 3],
+    pub color: [f32; 4],
     pub tex_coords: [f32; 2],
 }
 
@@ -14,7 +16,7 @@ impl Vertex {
         0 => Float32x3,
 
         // The face color
-        1 => Float32x3,
+        1 => Float32x4,
 
         // The texture coordinates
         2 => Float32x2,
@@ -31,3 +33,124 @@ impl Vertex {
 
 unsafe impl bytemuck::Pod for Vertex {}
 unsafe impl bytemuck::Zeroable for Vertex {}
+
+/// One rectangle, axis aligned until it is transformed, expanded into a
+/// quad by `vs_quad`. Kept small, since there is one for every glyph.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Quad {
+    pub rect: [f32; 4],
+    pub uv: [f32; 4],
+    pub color: [f32; 4],
+    pub flags: u32,
+
+    // Which of `Frame.styles` to draw with
+    pub style: u32,
+}
+
+impl Quad {
+    /// Ignore the texture and just fill with the color
+    pub const SOLID: u32 = 1;
+
+    /// Cut the corners with `radii` and draw the border
+    pub const ROUNDED: u32 = 2;
+
+    /// Fill with the gradient in `stops` instead of the color. Along the
+    /// line from `uv.xy` to `uv.zw`, relative to the center of the quad.
+    pub const GRADIENT: u32 = 4;
+
+    /// Make the gradient go out from `uv.xy`, reaching the end at radius `uv.z`
+    pub const RADIAL: u32 = 8;
+
+    /// Blur the edges of the rounded rectangle, by `uv.x` pixels, into a shadow
+    pub const SHADOW: u32 = 16;
+
+    /// The texture has already been multiplied by its alpha
+    pub const PREMULTIPLIED: u32 = 32;
+
+    const ATTRIBS: [VertexAttribute; 5] = vertex_attr_array![
+        // The corners, before they are transformed
+        0 => Float32x4,
+
+        // The corners in the texture
+        1 => Float32x4,
+
+        // The face color
+        2 => Float32x4,
+
+        // How to draw it
+        3 => Uint32,
+
+        // Where the rest of it is in the style buffer
+        4 => Uint32,
+    ];
+
+    /// The smallest axis aligned rect on screen the quad fits in.
+    /// The styles are the ones of the frame the quad is in.
+    pub fn bounds(&self, styles: &[QuadStyle]) -> [f32; 4] {
+        let [x0, y0, x1, y1] = self.rect;
+        let area = Area(Vec2::new(x0, y0), Vec2::new(x1, y1));
+        let transform = styles[self.style as usize].transform;
+        let bounds = Transform::from_array(transform).bounds(area);
+
+        return [bounds.0.x, bounds.0.y, bounds.1.x, bounds.1.y];
+    }
+
+    pub fn desc<'a>() -> VertexBufferLayout<'a> {
+        return VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as BufferAddress,
+            step_mode: VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        };
+    }
+}
+
+unsafe impl bytemuck::Pod for Quad {}
+unsafe impl bytemuck::Zeroable for Quad {}
+
+/// The parts of a quad that plain rects and glyphs leave alone, looked up
+/// by index. Laid out like `QuadStyle` in the shader.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct QuadStyle {
+    // Only used by rounded rectangles
+    pub radii: [f32; 4],
+    pub border_color: [f32; 4],
+
+    // Where the rect ends up on screen, see `Transform::to_array`
+    pub transform: [f32; 6],
+
+    // Only used by gradients, the first stop and how many there are
+    pub stops: [u32; 2],
+
+    pub border_width: f32,
+    pub _padding: [f32; 3],
+}
+
+impl Default for QuadStyle {
+    fn default() -> Self {
+        return QuadStyle {
+            radii: [0.; 4],
+            border_color: [0.; 4],
+            transform: Transform::IDENTITY.to_array(),
+            stops: [0; 2],
+            border_width: 0.,
+            _padding: [0.; 3],
+        };
+    }
+}
+
+unsafe impl bytemuck::Pod for QuadStyle {}
+unsafe impl bytemuck::Zeroable for QuadStyle {}
+
+/// A color somewhere along a gradient, laid out like `Stop` in the shader.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Stop {
+    pub color: [f32; 4],
+    pub offset: f32,
+    pub _padding: [f32; 3],
+}
+
+unsafe impl bytemuck::Pod for Stop {}
+unsafe impl bytemuck::Zeroable for Stop {}
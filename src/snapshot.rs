@@ -0,0 +1,951 @@
+//! Golden image tests.
+//!
+//! Trees are drawn with the CPU rasterizer, so the results don't depend on
+//! which GPU (if any) the tests run on, and compared against the reference
+//! images in `res/snapshots`. Run with `UPDATE_SNAPSHOTS=1` to (re)write the
+//! references. Failing tests leave the actual image and a diff in
+//! `target/snapshots`.
+
+use std::{env, fs, path::PathBuf, time::Duration};
+
+use winit::dpi::PhysicalSize;
+
+use crate::layout::*;
+use crate::ruglet::*;
+
+// How far apart a channel can be before we call it different
+const TOLERANCE: u8 = 2;
+
+// How many pixels are allowed to be different
+const MAX_DIFFERENT: usize = 0;
+
+// The size is in physical pixels, and the tree is laid out in logical ones
+fn draw(
+    tree: &mut Tree,
+    root: usize,
+    text: &TextBook,
+    images: &mut ImageBook,
+    size: (u32, u32),
+    scale: f32,
+) -> TextureData {
+    let size = PhysicalSize::new(size.0, size.1);
+
+    let mut atlas = FontAtlas::new();
+    let mut frame = Frame::with_scale(size, scale);
+    tree.build(root, &mut frame, &mut atlas, text, images);
+
+    return Software::new(size).render(frame);
+}
+
+fn assert_snapshot(name: &str, actual: &TextureData) {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let reference = root.join("res/snapshots").join(format!("{}.png", name));
+
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(reference.parent().unwrap()).unwrap();
+        actual.save_png(&reference).unwrap();
+        return;
+    }
+
+    // A missing reference is a failure too, so a new test can't pass by accident
+    if !reference.exists() {
+        panic!(
+            "snapshot {}: no reference at {}, run with UPDATE_SNAPSHOTS=1 to write it",
+            name,
+            reference.display()
+        );
+    }
+
+    let expected = TextureData::load(&reference).unwrap();
+    let output = root.join("target/snapshots");
+
+    if expected.dimensions != actual.dimensions {
+        fs::create_dir_all(&output).unwrap();
+        actual
+            .save_png(output.join(format!("{}.actual.png", name)))
+            .unwrap();
+
+        panic!(
+            "snapshot {}: expected {:?} image, got {:?}",
+            name, expected.dimensions, actual.dimensions
+        );
+    }
+
+    // Red where the images differ, a faded copy of the reference elsewhere
+    let mut different = 0;
+    let mut diff = Vec::with_capacity(actual.bytes.len());
+    for (a, e) in actual.bytes.chunks(4).zip(expected.bytes.chunks(4)) {
+        if a.iter().zip(e).any(|(a, e)| a.abs_diff(*e) > TOLERANCE) {
+            different += 1;
+            diff.extend_from_slice(&[0xff, 0, 0, 0xff]);
+        } else {
+            let gray = (e[0] as u16 + e[1] as u16 + e[2] as u16) / 3;
+            diff.extend_from_slice(&[gray as u8 / 4, gray as u8 / 4, gray as u8 / 4, 0xff]);
+        }
+    }
+
+    if different > MAX_DIFFERENT {
+        fs::create_dir_all(&output).unwrap();
+        actual
+            .save_png(output.join(format!("{}.actual.png", name)))
+            .unwrap();
+        TextureData::new(diff, actual.dimensions)
+            .save_png(output.join(format!("{}.diff.png", name)))
+            .unwrap();
+
+        panic!(
+            "snapshot {}: {} pixels differ, see {}",
+            name,
+            different,
+            output.display()
+        );
+    }
+}
+
+#[test]
+fn chapter_list() {
+    let mut tree = Tree::new();
+    let mut text = TextBook::new();
+
+    let chapters = ["Chapter 1", "Chapter 2", "Chapter 3"]
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let label = tree.add(NodeKind::Text(text.add(name)), vec![]);
+            return tree.add(NodeKind::Clickable(i), vec![label]);
+        })
+        .collect();
+
+    let pad = tree.add(NodeKind::Pad(25.0), chapters);
+    let root = tree.add(NodeKind::Scroll(0.0), vec![pad]);
+
+    assert_snapshot(
+        "chapter_list",
+        &draw(
+            &mut tree,
+            root,
+            &text,
+            &mut ImageBook::new(),
+            (640, 480),
+            2.,
+        ),
+    );
+}
+
+#[test]
+fn text_wrapping() {
+    let mut tree = Tree::new();
+    let mut text = TextBook::new();
+
+    let paragraph = text.add("The quick brown fox jumps over the lazy dog, twice.");
+    let paragraph = tree.add(NodeKind::Text(paragraph), vec![]);
+    let pad = tree.add(NodeKind::Pad(10.0), vec![paragraph]);
+    let root = tree.add(NodeKind::Scroll(0.0), vec![pad]);
+
+    assert_snapshot(
+        "text_wrapping",
+        &draw(
+            &mut tree,
+            root,
+            &text,
+            &mut ImageBook::new(),
+            (480, 480),
+            2.,
+        ),
+    );
+}
+
+// Eight lines of text in a scroll node, scrolled to `scroll`
+fn paragraphs(scroll: f32) -> (Tree, TextBook, usize) {
+    let mut tree = Tree::new();
+    let mut text = TextBook::new();
+
+    let paragraphs = (1..=8)
+        .map(|i| {
+            let line = text.add(&format!("Paragraph {}", i));
+            return tree.add(NodeKind::Text(line), vec![]);
+        })
+        .collect();
+
+    let pad = tree.add(NodeKind::Pad(25.0), paragraphs);
+    let root = tree.add(NodeKind::Scroll(scroll), vec![pad]);
+
+    return (tree, text, root);
+}
+
+#[test]
+fn scrolled() {
+    let (mut tree, text, root) = paragraphs(150.0);
+
+    assert_snapshot(
+        "scrolled",
+        &draw(
+            &mut tree,
+            root,
+            &text,
+            &mut ImageBook::new(),
+            (640, 480),
+            2.,
+        ),
+    );
+}
+
+#[test]
+fn scroll_clamped() {
+    let (mut tree, text, root) = paragraphs(10000.0);
+
+    let image = draw(
+        &mut tree,
+        root,
+        &text,
+        &mut ImageBook::new(),
+        (640, 480),
+        2.,
+    );
+
+    // The last paragraph and the padding after it end up at the bottom
+    assert!(matches!(tree.get(root), NodeKind::Scroll(scroll) if scroll == 410.));
+    assert_snapshot("scroll_clamped", &image);
+}
+
+#[test]
+fn smooth_scroll() {
+    let (mut tree, text, root) = paragraphs(0.0);
+
+    let scroll = |tree: &Tree| match tree.get(root) {
+        NodeKind::Scroll(scroll) => scroll,
+        _ => unreachable!(),
+    };
+
+    // Lay it out, so it knows how far it can go
+    draw(
+        &mut tree,
+        root,
+        &text,
+        &mut ImageBook::new(),
+        (640, 480),
+        2.,
+    );
+
+    // Gets closer every frame, without overshooting
+    tree.scroll_by(root, 100.);
+    let mut last = 0.;
+    while tree.animate(Duration::from_millis(16)) {
+        assert!(scroll(&tree) > last && scroll(&tree) < 100.);
+        last = scroll(&tree);
+    }
+    assert_eq!(scroll(&tree), 100.);
+
+    // Stops at either end
+    tree.scroll_by(root, 10000.);
+    while tree.animate(Duration::from_millis(16)) {}
+    assert_eq!(scroll(&tree), 410.);
+
+    tree.scroll_by(root, -10000.);
+    while tree.animate(Duration::from_millis(16)) {}
+    assert_eq!(scroll(&tree), 0.);
+}
+
+#[test]
+fn empty_pad() {
+    let mut tree = Tree::new();
+    let mut text = TextBook::new();
+
+    // Only as tall as its padding, even when that's less than a line
+    let empty = tree.add(NodeKind::Pad(10.0), vec![]);
+    tree.set_background(empty, Color::hex(0x3366cc));
+
+    let above = tree.add(NodeKind::Text(text.add("Above")), vec![]);
+    let below = tree.add(NodeKind::Text(text.add("Below")), vec![]);
+    let root = tree.add(NodeKind::Pad(25.0), vec![above, empty, below]);
+    tree.set_background(root, Color::hex(0x202020));
+
+    assert_snapshot(
+        "empty_pad",
+        &draw(
+            &mut tree,
+            root,
+            &text,
+            &mut ImageBook::new(),
+            (640, 480),
+            2.,
+        ),
+    );
+}
+
+#[test]
+fn backgrounds() {
+    let mut tree = Tree::new();
+    let mut text = TextBook::new();
+
+    let label = tree.add(NodeKind::Text(text.add("Highlighted")), vec![]);
+    let link = tree.add(NodeKind::Clickable(0), vec![label]);
+    tree.set_background(link, Color::linear(0.2, 0.0, 0.4, 1.));
+
+    let pad = tree.add(NodeKind::Pad(25.0), vec![link]);
+    tree.set_background(pad, Color::linear(0.0, 0.1, 0.2, 1.));
+    let root = tree.add(NodeKind::Scroll(0.0), vec![pad]);
+
+    assert_snapshot(
+        "backgrounds",
+        &draw(
+            &mut tree,
+            root,
+            &text,
+            &mut ImageBook::new(),
+            (640, 480),
+            2.,
+        ),
+    );
+}
+
+#[test]
+fn hover() {
+    let mut tree = Tree::new();
+    let mut text = TextBook::new();
+
+    let links: Vec<usize> = ["Chapter 1", "Chapter 2", "Chapter 3"]
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let label = tree.add(NodeKind::Text(text.add(name)), vec![]);
+            let link = tree.add(NodeKind::Clickable(i), vec![label]);
+            tree.set_background(link, Color::hex(0x282828));
+            tree.set_hover_background(link, Color::hex(0x3c3c3c));
+            return link;
+        })
+        .collect();
+
+    let pad = tree.add(NodeKind::Pad(25.0), links.clone());
+    let root = tree.add(NodeKind::Scroll(0.0), vec![pad]);
+
+    // Over the second chapter, once it's been laid out
+    tree.hover(root, Some(Vec2::new(100., 125.)));
+    let image = draw(
+        &mut tree,
+        root,
+        &text,
+        &mut ImageBook::new(),
+        (640, 480),
+        2.,
+    );
+
+    assert!(tree.is_hovered(links[1]) && tree.is_hovered(root));
+    assert!(!tree.is_hovered(links[0]) && !tree.is_hovered(links[2]));
+    assert!(matches!(tree.get(tree.hovered().unwrap()), NodeKind::Text(_)));
+    assert_snapshot("hover", &image);
+
+    // Leaving the window lets go of all of them
+    assert!(tree.hover(root, None));
+    assert!(tree.hovered().is_none() && !tree.is_hovered(links[1]));
+}
+
+#[test]
+fn tree_layers() {
+    let mut tree = Tree::new();
+    let mut text = TextBook::new();
+
+    let (links, labels): (Vec<usize>, Vec<usize>) = ["Underneath", "On top"]
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let label = tree.add(NodeKind::Text(text.add(name)), vec![]);
+            let link = tree.add(NodeKind::Clickable(i), vec![label]);
+            tree.set_background(link, Color::hex(0x282828 + 0x400000 * i as u32));
+            return (link, label);
+        })
+        .unzip();
+
+    let pad = tree.add(NodeKind::Pad(25.0), links.clone());
+    let root = tree.add(NodeKind::Scroll(0.0), vec![pad]);
+
+    // The second link is moved up onto the first, and drawn on top of it
+    tree.set_transform(links[1], Transform::translate(Vec2::new(40., -60.)));
+    tree.set_layer(links[1], 1);
+    let mouse = Vec2::new(100., 45.);
+    tree.hover(root, Some(mouse));
+    let image = draw(
+        &mut tree,
+        root,
+        &text,
+        &mut ImageBook::new(),
+        (640, 480),
+        2.,
+    );
+
+    // Whatever is on top gets the click and the hover, not the first one
+    assert_eq!(tree.click(root, mouse), Some(1));
+    assert!(tree.is_hovered(links[0]) && tree.is_hovered(links[1]));
+    assert_eq!(tree.hovered(), Some(labels[1]));
+    assert_snapshot("tree_layers", &image);
+
+    // On the same layer, the first one wins
+    tree.set_layer(links[1], 0);
+    tree.hover(root, Some(mouse));
+    assert_eq!(tree.click(root, mouse), Some(0));
+    assert_eq!(tree.hovered(), Some(labels[0]));
+}
+
+#[test]
+fn tree_transform() {
+    let mut tree = Tree::new();
+    let mut text = TextBook::new();
+
+    let label = tree.add(NodeKind::Text(text.add("Turned")), vec![]);
+    let link = tree.add(NodeKind::Clickable(7), vec![label]);
+
+    // Clicks only land inside the parent, so give it room below the link
+    let below = tree.add(NodeKind::Text(text.add("Below")), vec![]);
+    let pad = tree.add(NodeKind::Pad(25.0), vec![link, below]);
+    let root = tree.add(NodeKind::Scroll(0.0), vec![pad]);
+
+    // Laid out from (25, 25) to (295, 65), then stood up on its end
+    tree.set_transform(
+        link,
+        Transform::rotate(std::f32::consts::FRAC_PI_2).around(Vec2::new(160., 45.)),
+    );
+    draw(
+        &mut tree,
+        root,
+        &text,
+        &mut ImageBook::new(),
+        (640, 480),
+        2.,
+    );
+
+    // Clicks and hover follow it to where it's drawn
+    let (moved, original) = (Vec2::new(160., 150.), Vec2::new(40., 45.));
+    assert_eq!(tree.click(root, moved), Some(7));
+    assert_eq!(tree.click(root, original), None);
+
+    tree.hover(root, Some(moved));
+    assert!(tree.is_hovered(link));
+    tree.hover(root, Some(original));
+    assert!(!tree.is_hovered(link) && tree.is_hovered(pad));
+}
+
+#[test]
+fn image_fit() {
+    let mut tree = Tree::new();
+    let text = TextBook::new();
+    let mut images = ImageBook::new();
+
+    // A 60x40 image with a different color in each corner
+    let corners = (0..40)
+        .flat_map(|y| (0..60).map(move |x| (x, y)))
+        .flat_map(|(x, y)| match (x < 30, y < 20) {
+            (true, true) => [0xff, 0x40, 0x40, 0xff],
+            (false, true) => [0x40, 0xff, 0x40, 0xff],
+            (true, false) => [0x40, 0x40, 0xff, 0xff],
+            (false, false) => [0xff, 0xff, 0x40, 0xff],
+        })
+        .collect();
+    let image = images.add(TextureData::new(corners, (60, 40)));
+
+    let fits = [Fit::Contain, Fit::Cover, Fit::Fill, Fit::None]
+        .iter()
+        .map(|fit| {
+            let node = tree.add(NodeKind::Image(image, *fit), vec![]);
+            tree.set_background(node, Color::linear(0.1, 0.1, 0.1, 1.));
+            return node;
+        })
+        .collect();
+
+    let pad = tree.add(NodeKind::Pad(20.0), fits);
+    let root = tree.add(NodeKind::Scroll(0.0), vec![pad]);
+
+    assert_snapshot(
+        "image_fit",
+        &draw(&mut tree, root, &text, &mut images, (320, 480), 1.),
+    );
+}
+
+#[test]
+fn decoded_image() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let title = TextureData::load(root.join("res/title.png")).unwrap();
+    assert_eq!(title.dimensions, (1226, 222));
+    assert_eq!(title.bytes.len(), 1226 * 222 * 4);
+
+    // Cut short, or not an image at all
+    let bytes = fs::read(root.join("res/title.png")).unwrap();
+    assert!(matches!(
+        TextureData::decode(&bytes[..100]),
+        Err(ImageError::Png(_))
+    ));
+    let err = TextureData::decode(b"GIF89a").err().unwrap();
+    assert!(matches!(err, ImageError::Unsupported));
+    assert_eq!(err.to_string(), "not a PNG or JPEG that can be decoded");
+
+    let mut tree = Tree::new();
+    let text = TextBook::new();
+    let mut images = ImageBook::new();
+    let image = tree.add(NodeKind::Image(images.add(title), Fit::Contain), vec![]);
+    let root = tree.add(NodeKind::Pad(20.0), vec![image]);
+
+    assert_snapshot(
+        "decoded_image",
+        &draw(&mut tree, root, &text, &mut images, (640, 160), 1.),
+    );
+}
+
+#[test]
+fn empty_image() {
+    let mut tree = Tree::new();
+    let mut text = TextBook::new();
+    let mut images = ImageBook::new();
+
+    let empty = images.add(TextureData::new(vec![], (0, 0)));
+    let fits = [Fit::Contain, Fit::Cover, Fit::Fill, Fit::None]
+        .iter()
+        .map(|fit| tree.add(NodeKind::Image(empty, *fit), vec![]))
+        .collect::<Vec<_>>();
+    let label = tree.add(NodeKind::Text(text.add("After")), vec![]);
+    let root = tree.add(NodeKind::Pad(20.0), [fits, vec![label]].concat());
+
+    let mut atlas = FontAtlas::new();
+    let mut frame = Frame::new(PhysicalSize::new(320, 480));
+    tree.build(root, &mut frame, &mut atlas, &text, &mut images);
+
+    // Nothing is uploaded or drawn for it, and nothing ends up at NaN
+    assert_eq!(frame.textures.len(), 1);
+    assert!(frame
+        .quads
+        .iter()
+        .all(|quad| quad.rect.iter().chain(&quad.uv).all(|v| v.is_finite())));
+}
+
+#[test]
+fn nested_clips() {
+    let size = PhysicalSize::new(320, 240);
+    let mut frame = Frame::new(size);
+
+    frame.push_clip(Area(Vec2::new(20., 20.), Vec2::new(200., 200.)));
+    frame.rect(frame.area, Color::linear(0.2, 0.0, 0.0, 1.));
+
+    frame.push_clip(Area(Vec2::new(100., 60.), Vec2::new(300., 220.)));
+    frame.rounded_rect(
+        Area(Vec2::new(80., 40.), Vec2::new(240., 180.)),
+        [30.; 4],
+        Color::linear(0.0, 0.5, 1.0, 1.),
+    );
+    frame.pop_clip();
+
+    frame.line(
+        Vec2::new(0., 0.),
+        Vec2::new(320., 240.),
+        Stroke::new(6., Color::linear(1.0, 1.0, 0.0, 1.)),
+    );
+    frame.pop_clip();
+
+    assert_snapshot("nested_clips", &Software::new(size).render(frame));
+}
+
+#[test]
+fn layers() {
+    let size = PhysicalSize::new(320, 240);
+    let mut frame = Frame::new(size);
+
+    // Drawn first, but on top of everything else
+    frame.set_layer(1);
+    frame.rounded_rect(
+        Area(Vec2::new(120., 80.), Vec2::new(280., 200.)),
+        [20.; 4],
+        Color::linear(1.0, 0.8, 0.0, 1.),
+    );
+
+    // Drawn last, but underneath everything else
+    frame.set_layer(-1);
+    frame.rect(frame.area, Color::linear(0.0, 0.1, 0.2, 1.));
+
+    frame.set_layer(0);
+    frame.rect(
+        Area(Vec2::new(40., 40.), Vec2::new(200., 160.)),
+        Color::linear(0.6, 0.0, 0.2, 1.),
+    );
+
+    assert_snapshot("layers", &Software::new(size).render(frame));
+}
+
+#[test]
+fn textures() {
+    let size = PhysicalSize::new(320, 240);
+    let mut frame = Frame::new(size);
+    frame.set_clear_color(Color::linear(0.1, 0.1, 0.1, 1.));
+
+    // A checkerboard and a stripe, both 2x2
+    let checker = TextureId::unique();
+    let white = [0xff; 4];
+    let black = [0, 0, 0, 0xff];
+    frame.set_texture(
+        checker,
+        TextureData::new([white, black, black, white].concat(), (2, 2)),
+    );
+
+    let stripes = TextureId::unique();
+    let red = [0xff, 0, 0, 0xff];
+    let blue = [0, 0, 0xff, 0xff];
+    frame.set_texture(
+        stripes,
+        TextureData::new([red, blue, red, blue].concat(), (2, 2)),
+    );
+
+    // Alternate between them, with a solid fill in between
+    let full = Area(Vec2::zero(), Vec2::new(1., 1.));
+    frame.quad(
+        checker,
+        Area(Vec2::new(20., 20.), Vec2::new(140., 140.)),
+        full,
+        Color::WHITE,
+    );
+    frame.rect(
+        Area(Vec2::new(100., 100.), Vec2::new(220., 220.)),
+        Color::linear(0.2, 0.6, 0.2, 1.),
+    );
+    frame.quad(
+        stripes,
+        Area(Vec2::new(180., 20.), Vec2::new(300., 140.)),
+        full,
+        Color::WHITE,
+    );
+    frame.quad(
+        checker,
+        Area(Vec2::new(180., 160.), Vec2::new(300., 220.)),
+        full,
+        Color::linear(1., 1., 0., 1.),
+    );
+
+    assert_snapshot("textures", &Software::new(size).render(frame));
+}
+
+#[test]
+fn translucent() {
+    let size = PhysicalSize::new(320, 240);
+    let mut frame = Frame::new(size);
+    frame.set_clear_color(Color::hex(0x203040));
+
+    frame.rect(
+        Area(Vec2::new(20., 20.), Vec2::new(180., 180.)),
+        Color::hex(0xffcc00),
+    );
+
+    // Overlays that let what's underneath show through
+    frame.rect(
+        Area(Vec2::new(100., 60.), Vec2::new(300., 140.)),
+        Color::srgb(255, 255, 255).with_alpha(0.5),
+    );
+    frame.bordered_rect(
+        Area(Vec2::new(60., 100.), Vec2::new(260., 220.)),
+        [24.; 4],
+        Color::hex(0xff0066).with_alpha(0.4),
+        Border {
+            width: 8.,
+            color: Color::BLACK.with_alpha(0.8),
+        },
+    );
+    frame.line(
+        Vec2::new(0., 240.),
+        Vec2::new(320., 0.),
+        Stroke::new(12., Color::hex(0x00ffcc).with_alpha(0.5)),
+    );
+
+    assert_snapshot("translucent", &Software::new(size).render(frame));
+}
+
+#[test]
+fn gradients() {
+    let size = PhysicalSize::new(320, 240);
+    let mut frame = Frame::new(size);
+    frame.set_clear_color(Color::hex(0x202020));
+
+    // Three stops, left to right
+    frame.gradient(
+        Area(Vec2::new(10., 10.), Vec2::new(150., 60.)),
+        &Gradient::linear(
+            0.,
+            &[
+                (0., Color::hex(0xff0000)),
+                (0.5, Color::hex(0x00ff00)),
+                (1., Color::hex(0x0000ff)),
+            ],
+        ),
+    );
+
+    // Fading out diagonally, over what's already there
+    frame.rect(
+        Area(Vec2::new(170., 10.), Vec2::new(310., 110.)),
+        Color::hex(0xffffff),
+    );
+    frame.gradient(
+        Area(Vec2::new(170., 10.), Vec2::new(310., 110.)),
+        &Gradient::linear(
+            std::f32::consts::FRAC_PI_4,
+            &[
+                (0., Color::hex(0x0066ff)),
+                (1., Color::hex(0x0066ff).with_alpha(0.)),
+            ],
+        ),
+    );
+
+    // A hard stop in the middle
+    frame.rounded_gradient(
+        Area(Vec2::new(10., 80.), Vec2::new(150., 130.)),
+        [25.; 4],
+        &Gradient::linear(
+            std::f32::consts::FRAC_PI_2,
+            &[(0.5, Color::hex(0xffcc00)), (0.5, Color::hex(0xff6600))],
+        ),
+    );
+
+    // Off center, past the radius is the last stop
+    frame.gradient(
+        Area(Vec2::new(10., 150.), Vec2::new(310., 230.)),
+        &Gradient::radial(
+            Vec2::new(0.25, 0.5),
+            60.,
+            &[
+                (0., Color::WHITE),
+                (0.5, Color::hex(0xff00cc)),
+                (1., Color::BLACK),
+            ],
+        ),
+    );
+
+    assert_snapshot("gradients", &Software::new(size).render(frame));
+}
+
+#[test]
+fn shadows_and_blur() {
+    let size = PhysicalSize::new(320, 240);
+    let mut frame = Frame::new(size);
+    frame.set_clear_color(Color::hex(0xe0e0e0));
+
+    // A card lifted off the page
+    let card = Area(Vec2::new(20., 20.), Vec2::new(140., 100.));
+    frame.shadow(
+        card,
+        12.,
+        6.,
+        Vec2::new(0., 4.),
+        Color::BLACK.with_alpha(0.5),
+    );
+    frame.rounded_rect(card, [12.; 4], Color::WHITE);
+
+    // Stripes for the backdrop to blur
+    for i in 0..16 {
+        let x = 160. + i as f32 * 10.;
+        frame.rect(
+            Area(Vec2::new(x, 0.), Vec2::new(x + 5., 240.)),
+            Color::hex(0x2040c0),
+        );
+    }
+
+    // Frosted glass over the stripes and the edge of the page
+    let glass = Area(Vec2::new(120., 120.), Vec2::new(280., 220.));
+    frame.backdrop(glass, 16., 4.);
+    frame.rounded_rect(glass, [16.; 4], Color::WHITE.with_alpha(0.2));
+
+    assert_snapshot("shadows_and_blur", &Software::new(size).render(frame));
+}
+
+#[test]
+fn transforms() {
+    let size = PhysicalSize::new(320, 240);
+    let mut frame = Frame::new(size);
+    frame.set_clear_color(Color::hex(0x202020));
+
+    // A card turned around its center, with its shadow
+    let card = Area(Vec2::new(30., 30.), Vec2::new(130., 100.));
+    frame.push_transform(Transform::rotate(0.3).around(Vec2::new(80., 65.)));
+    frame.shadow(
+        card,
+        10.,
+        4.,
+        Vec2::new(0., 3.),
+        Color::BLACK.with_alpha(0.6),
+    );
+    frame.bordered_rect(
+        card,
+        [10.; 4],
+        Color::hex(0x3366cc),
+        Border {
+            width: 3.,
+            color: Color::WHITE,
+        },
+    );
+    frame.pop_transform();
+
+    // Zoomed in, lines get thicker and edges stay sharp
+    frame.push_transform(Transform::translate(Vec2::new(170., 20.)).then(Transform::scale(2., 2.)));
+    frame.rounded_rect(
+        Area(Vec2::new(0., 0.), Vec2::new(60., 40.)),
+        [8.; 4],
+        Color::hex(0xcc6633),
+    );
+    frame.line(
+        Vec2::new(5., 35.),
+        Vec2::new(55., 5.),
+        Stroke::new(2., Color::WHITE),
+    );
+
+    // Nested, and clipped to what's inside
+    frame.push_transform(Transform::translate(Vec2::new(0., 50.)));
+    frame.push_clip(Area(Vec2::new(0., 0.), Vec2::new(40., 20.)));
+    frame.gradient(
+        Area(Vec2::new(0., 0.), Vec2::new(60., 30.)),
+        &Gradient::linear(
+            0.,
+            &[(0., Color::hex(0xffcc00)), (1., Color::hex(0x00ccff))],
+        ),
+    );
+    frame.pop_clip();
+    frame.pop_transform();
+    frame.pop_transform();
+
+    // Squashed and turned, so the gradient follows along
+    frame.push_transform(
+        Transform::rotate(-0.5)
+            .then(Transform::scale(1., 0.5))
+            .around(Vec2::new(90., 190.)),
+    );
+    frame.rounded_gradient(
+        Area(Vec2::new(30., 150.), Vec2::new(150., 230.)),
+        [20.; 4],
+        &Gradient::radial(
+            Vec2::new(0.5, 0.5),
+            60.,
+            &[(0., Color::WHITE), (1., Color::hex(0x6633cc))],
+        ),
+    );
+    frame.pop_transform();
+
+    assert_snapshot("transforms", &Software::new(size).render(frame));
+}
+
+#[test]
+fn joins_and_caps() {
+    let size = PhysicalSize::new(360, 240);
+    let mut frame = Frame::new(size);
+    frame.set_clear_color(Color::linear(0.1, 0.1, 0.1, 1.));
+
+    // A sharp turn for each join, one to a column
+    for (i, join) in [Join::Miter, Join::Round, Join::Bevel].into_iter().enumerate() {
+        let x = 120. * i as f32;
+        frame.polyline(
+            &[
+                Vec2::new(x + 20., 110.),
+                Vec2::new(x + 60., 30.),
+                Vec2::new(x + 100., 110.),
+            ],
+            Stroke {
+                join,
+                ..Stroke::new(16., Color::hex(0x3399ff))
+            },
+        );
+    }
+
+    // Each cap, with a thin line showing where the points are
+    for (i, cap) in [Cap::Butt, Cap::Round, Cap::Square].into_iter().enumerate() {
+        let x = 120. * i as f32;
+        let (from, to) = (Vec2::new(x + 30., 180.), Vec2::new(x + 90., 180.));
+        frame.line(
+            from,
+            to,
+            Stroke {
+                cap,
+                ..Stroke::new(24., Color::hex(0xffcc00))
+            },
+        );
+        frame.line(from, to, Stroke::new(1., Color::BLACK));
+    }
+
+    assert_snapshot("joins_and_caps", &Software::new(size).render(frame));
+}
+
+#[test]
+fn scale_change() {
+    let paragraph = || {
+        let mut tree = Tree::new();
+        let mut text = TextBook::new();
+        let label = tree.add(NodeKind::Text(text.add("Sharp at any scale")), vec![]);
+        let root = tree.add(NodeKind::Pad(20.0), vec![label]);
+        return (tree, text, root);
+    };
+
+    // Same logical width at both scales, so the text fits the same way
+    let (mut tree, text, root) = paragraph();
+    let mut images = ImageBook::new();
+    let mut atlas = FontAtlas::new();
+    let mut frame = Frame::with_scale(PhysicalSize::new(320, 240), 1.);
+    tree.build(root, &mut frame, &mut atlas, &text, &mut images);
+
+    let size = PhysicalSize::new(640, 480);
+    let mut frame = Frame::with_scale(size, 2.);
+    tree.build(root, &mut frame, &mut atlas, &text, &mut images);
+    let rescaled = Software::new(size).render(frame);
+
+    let (mut tree, text, root) = paragraph();
+    let fresh = draw(&mut tree, root, &text, &mut images, (640, 480), 2.);
+
+    assert!(rescaled.bytes == fresh.bytes, "text differs after a scale change");
+}
+
+#[test]
+fn atlas_sent_once() {
+    let mut tree = Tree::new();
+    let mut text = TextBook::new();
+    let mut images = ImageBook::new();
+    let mut atlas = FontAtlas::new();
+
+    let label = tree.add(NodeKind::Text(text.add("Only once")), vec![]);
+    let root = tree.add(NodeKind::Pad(20.0), vec![label]);
+
+    let size = PhysicalSize::new(320, 240);
+    let mut frame = Frame::new(size);
+    tree.build(root, &mut frame, &mut atlas, &text, &mut images);
+    assert_eq!(frame.textures.len(), 1);
+
+    // No new glyphs, so the atlas isn't built or sent again
+    let mut frame = Frame::new(size);
+    tree.build(root, &mut frame, &mut atlas, &text, &mut images);
+    assert!(frame.textures.is_empty());
+}
+
+#[test]
+fn device_lost() {
+    // Needs a GPU, or at least wgpu's software adapter
+    let size = PhysicalSize::new(320, 240);
+    let mut headless = match pollster::block_on(Headless::new(size)) {
+        Ok(headless) => headless,
+        Err(RugletError::NoAdapter) => return,
+        Err(err) => panic!("{}", err),
+    };
+
+    let mut tree = Tree::new();
+    let mut text = TextBook::new();
+    let mut images = ImageBook::new();
+    let mut atlas = FontAtlas::new();
+
+    // The image and the atlas are only sent along with the first frame
+    let image = images.add(TextureData::new(
+        [[0xff, 0x40, 0x40, 0xff], [0x40, 0x40, 0xff, 0xff]].concat(),
+        (2, 1),
+    ));
+    let image = tree.add(NodeKind::Image(image, Fit::Fill), vec![]);
+    let label = tree.add(NodeKind::Text(text.add("Still here")), vec![]);
+    let pad = tree.add(NodeKind::Pad(20.0), vec![image, label]);
+    let root = tree.add(NodeKind::Scroll(0.0), vec![pad]);
+
+    let mut frame = Frame::new(size);
+    tree.build(root, &mut frame, &mut atlas, &text, &mut images);
+    let before = headless.render(frame).unwrap();
+
+    // Everything made with the old device has to be made again
+    headless.painter.device.destroy();
+
+    let mut frame = Frame::new(size);
+    tree.build(root, &mut frame, &mut atlas, &text, &mut images);
+    let after = headless.render(frame).unwrap();
+
+    assert!(before.bytes == after.bytes, "frames differ after device loss");
+}